@@ -0,0 +1,201 @@
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::agents::worktree::{WorktreeError, WorktreeInfo};
+use crate::tasks::Task;
+use crate::utils::process::spawn_and_capture_output;
+
+/// What the hub does with files a worker changed outside its task's boundaries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoundaryPolicy {
+    /// Only flag them in the agent's result.
+    #[default]
+    Report,
+    /// Restore them to the base commit.
+    Revert,
+    /// Fail the agent, so the task is retried or fails.
+    Fail,
+}
+
+impl std::str::FromStr for BoundaryPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "report" | "" => Ok(BoundaryPolicy::Report),
+            "revert" => Ok(BoundaryPolicy::Revert),
+            "fail" => Ok(BoundaryPolicy::Fail),
+            other => Err(format!("unknown boundary policy '{}'", other)),
+        }
+    }
+}
+
+/// Compiles path globs. `*` stays within one directory; `**` crosses them.
+pub fn path_globs(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+    }
+    builder.build()
+}
+
+/// How a worker's changes compare to its task's `must_touch` / `may_touch`
+/// and the project's forbidden paths.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundaryReport {
+    pub violations: Vec<String>, // Changed files matching neither list, or a forbidden path
+    pub untouched: Vec<String>,  // `must_touch` patterns no change matched
+    pub reverted: bool,
+}
+
+impl BoundaryReport {
+    /// Checks `changed` against the task and the `forbidden` globs. Returns
+    /// `None` if there are no boundaries to check.
+    pub fn check(task: &Task, forbidden: &[String], changed: &[String]) -> Result<Option<Self>, globset::Error> {
+        let bounded = !task.must_touch.is_empty() || !task.may_touch.is_empty();
+        if !bounded && forbidden.is_empty() {
+            return Ok(None);
+        }
+        let must = path_globs(&task.must_touch)?;
+        let may = path_globs(&task.may_touch)?;
+        let forbidden = path_globs(forbidden)?;
+
+        let violations = changed
+            .iter()
+            .filter(|file| forbidden.is_match(file) || (bounded && !must.is_match(file) && !may.is_match(file)))
+            .cloned()
+            .collect();
+        let mut untouched = Vec::new();
+        for pattern in &task.must_touch {
+            let glob = path_globs(std::slice::from_ref(pattern))?;
+            if !changed.iter().any(|file| glob.is_match(file)) {
+                untouched.push(pattern.clone());
+            }
+        }
+        Ok(Some(Self {
+            violations,
+            untouched,
+            reverted: false,
+        }))
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.violations.is_empty() && self.untouched.is_empty()
+    }
+
+    /// Markdown section appended to the agent's result.
+    pub fn summary(&self) -> String {
+        let mut summary = String::from("## File boundaries\n\n");
+        if !self.violations.is_empty() {
+            let action = if self.reverted { "reverted" } else { "kept" };
+            summary.push_str(&format!(
+                "Boundary violation: changed outside the allowed paths or in forbidden ones ({}): {}\n",
+                action,
+                self.violations.join(", ")
+            ));
+        }
+        if !self.untouched.is_empty() {
+            summary.push_str(&format!("Expected changes missing for: {}\n", self.untouched.join(", ")));
+        }
+        summary
+    }
+}
+
+/// Describes the task's boundaries for the worker's instructions.
+pub fn instructions(task: &Task) -> Option<String> {
+    if task.must_touch.is_empty() && task.may_touch.is_empty() {
+        return None;
+    }
+    let mut text = String::from("\n## File boundaries\n\n");
+    if !task.must_touch.is_empty() {
+        text.push_str(&format!("You are expected to change: {}\n", task.must_touch.join(", ")));
+    }
+    if !task.may_touch.is_empty() {
+        text.push_str(&format!("You may also change: {}\n", task.may_touch.join(", ")));
+    }
+    text.push_str("Changes to any other file are flagged as a boundary violation.\n");
+    Some(text)
+}
+
+/// Restores `files` to their state at the worktree's base commit, deleting
+/// those that did not exist there.
+pub fn revert_files(worktree: &WorktreeInfo, files: &[String]) -> Result<(), WorktreeError> {
+    let path = worktree.path.to_string_lossy();
+    for file in files {
+        let in_base = format!("{}:{}", worktree.base_commit, file);
+        if spawn_and_capture_output("git", &["-C", &path, "cat-file", "-e", &in_base]).is_ok() {
+            spawn_and_capture_output("git", &["-C", &path, "checkout", &worktree.base_commit, "--", file])?;
+        } else {
+            spawn_and_capture_output("git", &["-C", &path, "rm", "-q", "-f", "--ignore-unmatch", "--", file])?;
+            let full_path = Path::new(&*path).join(file);
+            if full_path.exists() {
+                std::fs::remove_file(full_path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::worktree::{changed_files, create_worktree, tests::init_repo};
+    use tempfile::tempdir;
+
+    fn task(must_touch: &[&str], may_touch: &[&str]) -> Task {
+        let mut task: Task =
+            serde_json::from_str(r#"{"id": "t1", "description": "d", "agent_type": null}"#).unwrap();
+        task.must_touch = must_touch.iter().map(|p| p.to_string()).collect();
+        task.may_touch = may_touch.iter().map(|p| p.to_string()).collect();
+        task
+    }
+
+    #[test]
+    fn test_check_flags_changes_outside_boundaries() {
+        let changed = vec!["src/lib.rs".to_string(), "src/nested/mod.rs".to_string(), "Cargo.toml".to_string()];
+        assert!(BoundaryReport::check(&task(&[], &[]), &[], &changed).unwrap().is_none());
+
+        let report = BoundaryReport::check(&task(&["src/*.rs", "docs/**"], &["Cargo.toml"]), &[], &changed)
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.violations, vec!["src/nested/mod.rs".to_string()]);
+        assert_eq!(report.untouched, vec!["docs/**".to_string()]);
+        assert!(report.summary().contains("Boundary violation"));
+
+        // Forbidden paths apply even to tasks without boundaries of their own
+        let report = BoundaryReport::check(&task(&[], &[]), &["*.toml".to_string()], &changed)
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.violations, vec!["Cargo.toml".to_string()]);
+    }
+
+    #[test]
+    fn test_revert_restores_and_removes_files() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        init_repo(&repo);
+        std::fs::write(repo.join("secrets.toml"), "token = 1\n").unwrap();
+        let root = repo.to_string_lossy();
+        spawn_and_capture_output("git", &["-C", &root, "add", "secrets.toml"]).unwrap();
+        spawn_and_capture_output("git", &["-C", &root, "commit", "-q", "-m", "secrets"]).unwrap();
+        let info = create_worktree(&repo, &dir.path().join("wt"), "vibe/t1").unwrap();
+        std::fs::write(info.path.join("README.md"), "changed\n").unwrap();
+        std::fs::write(info.path.join("stray.txt"), "stray\n").unwrap();
+        let wt = info.path.to_string_lossy().to_string();
+        spawn_and_capture_output("git", &["-C", &wt, "mv", "secrets.toml", "a.rs"]).unwrap();
+
+        // A rename lists both paths, so the old one is checked and restored too
+        let changed = changed_files(&info).unwrap();
+        assert_eq!(changed, vec!["README.md", "a.rs", "secrets.toml", "stray.txt"]);
+        revert_files(&info, &changed).unwrap();
+        assert!(changed_files(&info).unwrap().is_empty());
+        assert_eq!(std::fs::read_to_string(info.path.join("README.md")).unwrap(), "hello\n");
+        assert_eq!(std::fs::read_to_string(info.path.join("secrets.toml")).unwrap(), "token = 1\n");
+        assert!(!info.path.join("a.rs").exists());
+        assert!(!info.path.join("stray.txt").exists());
+    }
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::utils::process::signal_process_group;
+
+/// Lines of a check's output kept for the agent's result.
+const CHECK_OUTPUT_LINES: usize = 20;
+
+/// How long a check may run before it is killed and counted as failed.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A formatter or linter command from the project rules, run after a worker finished.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleCheck {
+    pub command: String,
+    pub passed: bool,
+    pub output: String, // Last lines of stdout and stderr
+}
+
+/// Runs `command` through `sh -c` in `dir`, killing it and whatever it started
+/// once `timeout` has passed.
+pub async fn run_check(dir: &Path, command: &str, timeout: Duration) -> RuleCheck {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0) // Own group, so a timeout reaches the command's children too
+        .kill_on_drop(true)
+        .spawn();
+    let (passed, output) = match child {
+        Ok(child) => {
+            let pgid = child.id();
+            match tokio::time::timeout(timeout, child.wait_with_output()).await {
+                Ok(Ok(output)) => {
+                    let combined = format!(
+                        "{}{}",
+                        String::from_utf8_lossy(&output.stdout),
+                        String::from_utf8_lossy(&output.stderr)
+                    );
+                    (output.status.success(), tail(&combined))
+                }
+                Ok(Err(e)) => (false, format!("could not run check: {}", e)),
+                Err(_) => {
+                    if let Some(pgid) = pgid {
+                        let _ = tokio::task::spawn_blocking(move || signal_process_group(pgid, "KILL")).await;
+                    }
+                    (false, format!("timed out after {}s", timeout.as_secs_f32()))
+                }
+            }
+        }
+        Err(e) => (false, format!("could not run check: {}", e)),
+    };
+    RuleCheck {
+        command: command.to_string(),
+        passed,
+        output,
+    }
+}
+
+fn tail(output: &str) -> String {
+    let lines: Vec<&str> = output.lines().collect();
+    lines[lines.len().saturating_sub(CHECK_OUTPUT_LINES)..].join("\n")
+}
+
+/// Markdown section appended to the agent's result.
+pub fn summary(checks: &[RuleCheck]) -> String {
+    let mut summary = String::from("## Rule checks\n\n");
+    for check in checks {
+        if check.passed {
+            summary.push_str(&format!("- `{}` passed\n", check.command));
+        } else {
+            summary.push_str(&format!("- `{}` failed:\n\n```\n{}\n```\n", check.command, check.output));
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_run_check_reports_status_and_output() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("marker"), "").unwrap();
+
+        let passed = run_check(dir.path(), "ls", CHECK_TIMEOUT).await;
+        assert!(passed.passed);
+        assert_eq!(passed.output, "marker");
+
+        let failed = run_check(dir.path(), "echo bad formatting >&2; exit 1", CHECK_TIMEOUT).await;
+        assert!(!failed.passed);
+        assert!(summary(&[passed, failed]).contains("bad formatting"));
+    }
+
+    #[tokio::test]
+    async fn test_run_check_times_out() {
+        let dir = tempdir().unwrap();
+        let started = std::time::Instant::now();
+
+        let check = run_check(dir.path(), "sleep 5 & sleep 5", Duration::from_millis(200)).await;
+        assert!(!check.passed);
+        assert!(check.output.contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+}
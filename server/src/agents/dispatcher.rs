@@ -1,130 +1,781 @@
 use std::env;
 use std::sync::Arc;
-use crate::tasks::{TaskGraph, Task};
-use crate::agents::spawner::AgentSpawner;
+use crate::sessions::{SessionStore, WsEvent};
+use crate::tasks::{ScheduledTask, TaskGraph, TaskGraphError, TaskSchedule, TaskState};
+use crate::agents::events::AgentEvent;
+use crate::agents::registry::{AgentRegistry, AgentStatus};
+use crate::agents::boundaries::{self, BoundaryPolicy, BoundaryReport};
+use crate::agents::checks;
+use crate::agents::merge::{self, ConflictPolicy, MergeError, MergeMode, MergeOutcome, MergeReport};
+use crate::agents::registry::Agent;
+use crate::agents::worktree::{self, WorkerIsolation, WorktreeInfo};
+use crate::tasks::Task;
+use crate::utils::process::{process_group_alive, signal_process_group};
+use crate::profiles::{ProfileCatalog, PromptProfile};
+use crate::project_sessions::ProjectSession;
+use crate::prompts::task_prompt;
+use crate::agents::spawner::{AgentControlError, AgentSpawner};
 use crate::config::ServerConfig;
 use std::collections::{HashMap, HashSet};
 use tracing::{info, error, warn};
 use std::path::PathBuf;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
+use chrono::{DateTime, Utc};
+
+/// Lines of a failed attempt's debug log carried over into the retry's instructions.
+const FAILED_ATTEMPT_LOG_LINES: usize = 40;
 
 #[derive(Clone)]
 pub struct TaskDispatcher {
     spawner: AgentSpawner,
+    registry: AgentRegistry,
     config: Arc<ServerConfig>,
     base_dir: PathBuf,
     // State management
-    task_queue: Arc<Mutex<Vec<(String, Task)>>>, // (session_id, Task)
-    running_agent: Arc<Mutex<Option<String>>>, // Some(agent_id) if busy
-    dispatched_tasks: Arc<Mutex<HashSet<(String, String)>>>, // (session_id, task_id) for idempotency
+    schedules: Arc<Mutex<HashMap<String, TaskSchedule>>>, // session_id -> task DAG
+    running_agents: Arc<Mutex<HashMap<String, String>>>, // agent_id -> session_id of in-flight workers
+    sessions: Option<Arc<SessionStore>>, // Receives task failure events when set
+    project_sessions: Option<Arc<RwLock<HashMap<String, ProjectSession>>>>, // Resolves project roots for worktrees
+    profiles: Option<Arc<ProfileCatalog>>, // Source of the configured prompt profile
+    merge_locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>, // session_id -> guard of its integration worktree
+    integrating: Arc<Mutex<HashSet<String>>>, // agent_ids whose finished work is being checked or merged
 }
 
 impl TaskDispatcher {
-    pub fn new(spawner: AgentSpawner, config: Arc<ServerConfig>, base_dir: PathBuf) -> Self {
+    pub fn new(
+        spawner: AgentSpawner,
+        registry: AgentRegistry,
+        config: Arc<ServerConfig>,
+        base_dir: PathBuf,
+    ) -> Self {
         Self { 
             spawner, 
+            registry,
             config, 
             base_dir,
-            task_queue: Arc::new(Mutex::new(Vec::new())),
-            running_agent: Arc::new(Mutex::new(None)),
-            dispatched_tasks: Arc::new(Mutex::new(HashSet::new())),
+            schedules: Arc::new(Mutex::new(HashMap::new())),
+            running_agents: Arc::new(Mutex::new(HashMap::new())),
+            sessions: None,
+            project_sessions: None,
+            profiles: None,
+            merge_locks: Arc::new(Mutex::new(HashMap::new())),
+            integrating: Arc::new(Mutex::new(HashSet::new())),
         }
     }
 
+    /// Lets the dispatcher find each session's project root, which worktree
+    /// isolation needs.
+    pub fn with_project_sessions(mut self, project_sessions: Arc<RwLock<HashMap<String, ProjectSession>>>) -> Self {
+        self.project_sessions = Some(project_sessions);
+        self
+    }
+
+    /// Layers the configured prompt profile into worker instructions.
+    pub fn with_profiles(mut self, profiles: Arc<ProfileCatalog>) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    /// Publishes final task failures to the session's WebSocket subscribers.
+    pub fn with_session_events(mut self, sessions: Arc<SessionStore>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    /// Advances the queue whenever an agent completes, exits or is stopped, so
+    /// failed attempts are noticed (and retried) even without a RESULT.md.
+    pub fn watch_agent_events(&self) {
+        let dispatcher = self.clone();
+        let mut events = self.registry.events().subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) if event.is_final() => dispatcher.on_agent_finished(event.agent_id()).await,
+                    Ok(_) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        // Statuses are re-read from the registry, so a resync is enough
+                        warn!("Dispatcher missed {} agent events", skipped);
+                        dispatcher.integrate_pending();
+                        dispatcher.process_queue().await;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
     /// Called by ResultWatcher when a new TASK_GRAPH.json is found.
-    pub async fn dispatch(&self, session_id: String, task_graph: TaskGraph) { 
+    /// Tasks already scheduled for the session are skipped; the graph is rejected
+    /// as a whole if merging it would leave a cycle or an unknown dependency.
+    pub async fn dispatch(&self, session_id: String, task_graph: TaskGraph) -> Result<(), TaskGraphError> { 
+        self.dispatch_from(session_id, task_graph, None).await
+    }
+
+    /// Like `dispatch`, for tasks proposed by `requested_by`. The agents that
+    /// work on them become its children.
+    pub async fn dispatch_from(
+        &self,
+        session_id: String,
+        task_graph: TaskGraph,
+        requested_by: Option<String>,
+    ) -> Result<(), TaskGraphError> {
         info!("Analyzing {} tasks for dispatch in session: {}", task_graph.tasks.len(), session_id);
 
         {
-            let mut queue = self.task_queue.lock();
-            let mut dispatched = self.dispatched_tasks.lock();
-            let mut added_count = 0;
-
-            for task in task_graph.tasks {
-                // Idempotency check
-                if dispatched.contains(&(session_id.clone(), task.id.clone())) {
-                    continue;
+            let mut schedules = self.schedules.lock();
+            let schedule = schedules
+                .entry(session_id.clone())
+                .or_insert_with(|| TaskSchedule::with_retry_policy(self.config.retry_policy()));
+            let added_count = schedule.merge_from(task_graph, requested_by.as_deref())?;
+            info!("Queued {} new tasks. Session {} now tracks {} tasks", added_count, session_id, schedule.tasks().len());
+        } // Guard dropped here
+
+        self.process_queue().await;
+        Ok(())
+    }
+
+    /// Frees the worker slot of an agent that finished and refills the queue.
+    async fn on_agent_finished(&self, agent_id: &str) {
+        // Completion and process exit both arrive here; only the first frees a slot
+        if self.running_agents.lock().remove(agent_id).is_some() {
+            info!("Agent {} finished. Checking queue...", agent_id);
+        }
+        self.spawn_integration(agent_id);
+        self.process_queue().await;
+    }
+
+    /// Integrates a finished worker's work in a task of its own, so its checks
+    /// and merge do not hold up the event loop or other workers. The queue is
+    /// refilled afterwards, as the worker's task may have completed.
+    fn spawn_integration(&self, agent_id: &str) {
+        if !self.integrating.lock().insert(agent_id.to_string()) {
+            return;
+        }
+        let dispatcher = self.clone();
+        let agent_id = agent_id.to_string();
+        tokio::spawn(async move {
+            dispatcher.integrate_finished_work(&agent_id).await;
+            dispatcher.integrating.lock().remove(&agent_id);
+            dispatcher.process_queue().await;
+        });
+    }
+
+    /// Integrates finished workers whose exit went unnoticed, e.g. because the
+    /// event was lost to lag or a leftover child kept the process group alive.
+    fn integrate_pending(&self) {
+        let pending: Vec<String> = self
+            .registry
+            .list_agents()
+            .into_iter()
+            .filter(|agent| !agent.status.is_active())
+            .filter(|agent| {
+                agent.worktree.as_ref().is_some_and(|w| w.diff_stat.is_none() && !w.removed)
+                    || (agent.status == AgentStatus::Completed && !agent.work_checked && self.holds_task(agent))
+            })
+            .map(|agent| agent.id)
+            .collect();
+        for agent_id in pending {
+            self.spawn_integration(&agent_id);
+        }
+    }
+
+    /// Checks a finished worker's changes and, in auto merge mode, merges its
+    /// branch. A worker may still commit after reporting completion, so this
+    /// does nothing while its process group is alive; the exit event or the
+    /// watchdog calls it again. Processes left behind by a worker that stayed
+    /// quiet past the idle limit are killed.
+    async fn integrate_finished_work(&self, agent_id: &str) {
+        let Some(agent) = self.registry.get_agent(agent_id) else {
+            return;
+        };
+        if process_running(&agent).await {
+            let idle_limit = self.config.agent_idle_timeout_secs;
+            let idle_for = (Utc::now() - agent.last_activity_at).num_seconds();
+            if idle_limit == 0 || idle_for <= idle_limit as i64 {
+                return;
+            }
+            if let Some(pgid) = agent.pid {
+                warn!("Killing processes agent {} left behind, idle for {}s", agent_id, idle_for);
+                if let Err(e) = blocking(move || signal_process_group(pgid, "KILL")).await {
+                    error!("Failed to kill processes of agent {}: {}", agent_id, e);
                 }
+            }
+        }
+        let task = {
+            let schedules = self.schedules.lock();
+            schedules
+                .get(&agent.session_id)
+                .and_then(|schedule| schedule.task_run_by(agent_id))
+                .map(|entry| entry.task.clone())
+        };
+        let session = self.project_session(&agent.session_id);
 
-                dispatched.insert((session_id.clone(), task.id.clone()));
-                queue.push((session_id.clone(), task));
-                added_count += 1;
+        // Boundaries first, so checks and the recorded diff see reverted files
+        if let (Some(task), Some(worktree)) = (&task, &agent.worktree) {
+            if worktree.diff_stat.is_none() {
+                self.enforce_boundaries(&agent, task, worktree, session.as_ref()).await;
             }
-            info!("Queued {} new tasks. Queue size: {}", added_count, queue.len());
-        } // Guards dropped here
+        }
+        if let (Some(task), Some(session)) = (&task, &session) {
+            if agent.rule_checks.is_none() && agent.status == AgentStatus::Completed {
+                self.run_rule_checks(&agent, task, session).await;
+            }
+        }
+        self.record_worktree_diff(agent_id).await;
+        // Lets its task complete and release the tasks depending on it
+        if let Err(e) = self.registry.mark_work_checked(agent_id) {
+            error!("Failed to record checks of agent {}: {}", agent_id, e);
+        }
 
-        self.process_queue().await;
+        // Re-read, as a violation or failed check may have failed the agent
+        let Some(agent) = self.registry.get_agent(agent_id) else {
+            return;
+        };
+        let Some(worktree) = &agent.worktree else {
+            return;
+        };
+        if self.config.merge_mode == MergeMode::Auto
+            && agent.status == AgentStatus::Completed
+            && worktree.merge.is_none()
+        {
+            match self.merge_agent(agent_id).await {
+                Ok(report) => info!("Merged agent {}: {:?}", agent_id, report.outcome),
+                Err(e) => error!("Failed to merge agent {}: {}", agent_id, e),
+            }
+        }
     }
 
-    /// Called by ResultWatcher when it detects an agent has completed (RESULT.md).
-    pub async fn on_agent_complete(&self, agent_id: &str) {
-        info!("Agent {} completed. Checking queue...", agent_id);
-        
+    /// Checks a finished worker's changes against its task's `must_touch` /
+    /// `may_touch` globs and the project's forbidden paths, records the outcome
+    /// in its result and applies the configured boundary policy.
+    async fn enforce_boundaries(&self, agent: &Agent, task: &Task, worktree: &WorktreeInfo, session: Option<&ProjectSession>) {
+        let changed = match blocking({
+            let worktree = worktree.clone();
+            move || worktree::changed_files(&worktree)
+        })
+        .await
         {
-            let mut running = self.running_agent.lock();
-            if let Some(current_id) = running.as_ref() {
-                if current_id == agent_id {
-                    *running = None;
-                } else {
-                    warn!("Completed agent {} does not match expected running agent {}", agent_id, current_id);
-                }
+            Ok(changed) => changed,
+            Err(e) => {
+                error!("Failed to list changes of agent {}: {}", agent.id, e);
+                return;
             }
-        } // Guard dropped
+        };
+        let forbidden = session.map(|s| s.rules.forbidden_paths.as_slice()).unwrap_or_default();
+        let mut report = match BoundaryReport::check(task, forbidden, &changed) {
+            Ok(Some(report)) if !report.is_clean() => report,
+            Ok(_) => return,
+            Err(e) => {
+                error!("Invalid path patterns for task {}: {}", task.id, e);
+                return;
+            }
+        };
 
-        self.process_queue().await;
+        if !report.violations.is_empty() {
+            warn!("Agent {} changed files outside task {}: {}", agent.id, task.id, report.violations.join(", "));
+            match self.config.boundary_policy {
+                BoundaryPolicy::Report => {}
+                BoundaryPolicy::Revert => match blocking({
+                    let (worktree, files) = (worktree.clone(), report.violations.clone());
+                    move || boundaries::revert_files(&worktree, &files)
+                })
+                .await
+                {
+                    Ok(()) => report.reverted = true,
+                    Err(e) => error!("Failed to revert boundary violations of agent {}: {}", agent.id, e),
+                },
+                BoundaryPolicy::Fail => {
+                    self.reject_work(agent, task, format!("boundary violation: changed {}", report.violations.join(", ")))
+                }
+            }
+        }
+        if let Err(e) = self.registry.append_result(&agent.id, &report.summary()) {
+            error!("Failed to record boundary check for agent {}: {}", agent.id, e);
+        }
     }
 
-    async fn process_queue(&self) {
+    /// Runs the formatter and linter commands of the project rules that apply to
+    /// the worker's changes. A failing check fails the worker.
+    async fn run_rule_checks(&self, agent: &Agent, task: &Task, session: &ProjectSession) {
+        let Some(worktree) = &agent.worktree else {
+            // Other workers edit the shared project root too, so a result there says nothing about this one
+            if !session.rules.check_commands(None).is_empty() {
+                let note = "## Rule checks\n\nSkipped: the worker did not run in its own worktree, so its changes cannot be told apart from other workers'.\n";
+                if let Err(e) = self.registry.append_result(&agent.id, note) {
+                    error!("Failed to record skipped rule checks for agent {}: {}", agent.id, e);
+                }
+            }
+            if let Err(e) = self.registry.set_rule_checks(&agent.id, Vec::new()) {
+                error!("Failed to record rule checks for agent {}: {}", agent.id, e);
+            }
+            return;
+        };
+        let changed = match blocking({
+            let worktree = worktree.clone();
+            move || worktree::changed_files(&worktree)
+        })
+        .await
         {
-            let running = self.running_agent.lock();
-            if running.is_some() {
+            Ok(changed) => changed,
+            Err(e) => {
+                error!("Failed to list changes of agent {}: {}", agent.id, e);
                 return;
             }
-        } // Drop running lock
+        };
+
+        let mut results = Vec::new();
+        for command in session.rules.check_commands(Some(&changed)) {
+            info!("Running rule check `{}` for agent {}", command, agent.id);
+            results.push(checks::run_check(&worktree.path, &command, checks::CHECK_TIMEOUT).await);
+        }
+        let failed: Vec<&str> = results.iter().filter(|check| !check.passed).map(|check| check.command.as_str()).collect();
+        if !failed.is_empty() {
+            self.reject_work(agent, task, format!("rule checks failed: {}", failed.join(", ")));
+        }
+        if !results.is_empty() {
+            if let Err(e) = self.registry.append_result(&agent.id, &checks::summary(&results)) {
+                error!("Failed to record rule checks for agent {}: {}", agent.id, e);
+            }
+        }
+        if let Err(e) = self.registry.set_rule_checks(&agent.id, results) {
+            error!("Failed to record rule checks for agent {}: {}", agent.id, e);
+        }
+    }
 
-        let next_task = {
-            let mut queue = self.task_queue.lock();
-            if queue.is_empty() {
+    /// Fails a worker whose completed work the hub rejected. Its task is still
+    /// waiting on these checks, so the failure is retried or reported like any other.
+    fn reject_work(&self, agent: &Agent, task: &Task, reason: String) {
+        if agent.status != AgentStatus::Completed {
+            return;
+        }
+        warn!("Rejecting work of agent {} on task {}: {}", agent.id, task.id, reason);
+        let status = AgentStatus::Failed(reason);
+        if self.registry.update_status(&agent.id, status.clone()).is_err() {
+            return;
+        }
+        self.registry.events().publish(AgentEvent::StatusChanged {
+            agent_id: agent.id.clone(),
+            session_id: agent.session_id.clone(),
+            status,
+        });
+    }
+
+    /// Merges a finished agent's branch into its session's integration branch.
+    /// Conflicts are handed to a resolver agent or raised as an interaction on
+    /// the agent, as configured.
+    pub async fn merge_agent(&self, agent_id: &str) -> Result<MergeReport, MergeError> {
+        let not_found = || MergeError::NotFound(agent_id.to_string());
+        let session_id = self.registry.get_agent(agent_id).ok_or_else(not_found)?.session_id;
+        // Manual and automatic merges of a session share its integration worktree
+        let merge_lock = self.merge_locks.lock().entry(session_id).or_default().clone();
+        let _merging = merge_lock.lock().await;
+
+        // Read under the lock, so a merge that just finished is seen
+        let agent = self.registry.get_agent(agent_id).ok_or_else(not_found)?;
+        let mut info = agent
+            .worktree
+            .clone()
+            .ok_or_else(|| MergeError::NoWorktree(agent_id.to_string()))?;
+        if agent.status.is_active() || process_running(&agent).await {
+            return Err(MergeError::StillRunning(agent_id.to_string()));
+        }
+
+        let integration_branch = merge::integration_branch(&agent.session_id);
+        if info.removed {
+            // Merged before, after which the branch was deleted
+            return Ok(MergeReport {
+                agent_id: agent_id.to_string(),
+                branch: info.branch,
+                integration_branch,
+                outcome: MergeOutcome::UpToDate,
+                resolver_task_id: None,
+                interaction_id: None,
+            });
+        }
+        let integration_dir = self.worktrees_dir(&agent.session_id).join("integration");
+        let outcome = blocking({
+            let (info, integration_branch) = (info.clone(), integration_branch.clone());
+            let message = format!("Uncommitted work of agent {}", agent_id);
+            move || {
+                merge::ensure_integration_worktree(&info.project_root, &integration_dir, &integration_branch)?;
+                merge::commit_pending_changes(&info.path, &message)?;
+                merge::merge_branch(&integration_dir, &info.branch)
+            }
+        })
+        .await?;
+
+        let mut report = MergeReport {
+            agent_id: agent_id.to_string(),
+            branch: info.branch.clone(),
+            integration_branch,
+            outcome: outcome.clone(),
+            resolver_task_id: None,
+            interaction_id: None,
+        };
+        if let MergeOutcome::Conflict { files } = &outcome {
+            warn!("Merging {} conflicts in {}", report.branch, files.join(", "));
+            let use_resolver = self.config.merge_conflict_policy == ConflictPolicy::Resolver
+                && self.config.worker_isolation == WorkerIsolation::Worktree;
+            if use_resolver {
+                report.resolver_task_id = self.schedule_resolver(&agent.session_id, &report, files).await;
+            }
+            if report.resolver_task_id.is_none() {
+                report.interaction_id = self.raise_merge_conflict(&agent, &report, files);
+            }
+        }
+
+        if !matches!(outcome, MergeOutcome::Conflict { .. }) {
+            // The work is on the integration branch now; a conflict keeps both for resolving
+            let removal = blocking({
+                let info = info.clone();
+                move || worktree::remove_worktree(&info)
+            });
+            match removal.await {
+                Ok(()) => info.removed = true,
+                Err(e) => warn!("Failed to remove worktree of merged agent {}: {}", agent_id, e),
+            }
+        }
+        info.merge = Some(outcome);
+        let summary = report.summary();
+        if let Err(e) = self
+            .registry
+            .set_worktree(agent_id, info)
+            .and_then(|_| self.registry.append_result(agent_id, &summary))
+        {
+            error!("Failed to record merge for agent {}: {}", agent_id, e);
+        }
+        Ok(report)
+    }
+
+    /// Queues a task whose agent merges both sides of a conflict on its own branch.
+    async fn schedule_resolver(&self, session_id: &str, report: &MergeReport, files: &[String]) -> Option<String> {
+        let task = Task {
+            agent_type: Some("resolver".to_string()),
+            ..Task::new(
+                format!("resolve-{}", report.branch.rsplit('/').next().unwrap_or(&report.branch)),
+                format!(
+                    "resolve the merge conflicts between `{}` and `{}` in {}. Merge `{}` and then `{}` into your branch, resolve the conflicts keeping the intent of both sides, and commit the result",
+                    report.branch,
+                    report.integration_branch,
+                    files.join(", "),
+                    report.integration_branch,
+                    report.branch
+                ),
+            )
+        };
+        let task_id = task.id.clone();
+        let graph = TaskGraph { tasks: vec![task] };
+        match self.dispatch_from(session_id.to_string(), graph, Some(report.agent_id.clone())).await {
+            Ok(()) => Some(task_id),
+            Err(e) => {
+                error!("Failed to schedule resolver for {}: {}", report.branch, e);
                 None
-            } else {
-                // FIFO
-                Some(queue.remove(0))
             }
-        }; // Drop queue lock
+        }
+    }
 
-        if let Some((session_id, task)) = next_task {
-            info!("Starting execution for task: {} - {}", task.id, task.description);
+    /// Asks the user to resolve a conflict by hand on the agent's branch.
+    fn raise_merge_conflict(&self, agent: &Agent, report: &MergeReport, files: &[String]) -> Option<String> {
+        let question = format!(
+            "Merging `{}` into `{}` conflicts in {}. Resolve the conflicts on `{}` and merge agent {} again.",
+            report.branch,
+            report.integration_branch,
+            files.join(", "),
+            report.branch,
+            agent.id
+        );
+        match self.registry.set_pending_interaction(&agent.id, question.clone()) {
+            Ok(interaction_id) => {
+                self.registry.events().publish(AgentEvent::Asked {
+                    agent_id: agent.id.clone(),
+                    session_id: agent.session_id.clone(),
+                    interaction_id: interaction_id.clone(),
+                    question,
+                });
+                Some(interaction_id)
+            }
+            Err(e) => {
+                error!("Failed to raise merge conflict for agent {}: {}", agent.id, e);
+                None
+            }
+        }
+    }
 
-            let agent_type = task.agent_type.clone().unwrap_or_else(|| "worker".to_string());
-            let agent_id = uuid::Uuid::new_v4().to_string(); 
+    /// Returns the scheduling state of every task known for a session.
+    pub fn task_states(&self, session_id: &str) -> Vec<(String, TaskState)> {
+        let schedules = self.schedules.lock();
+        schedules
+            .get(session_id)
+            .map(|schedule| {
+                schedule
+                    .tasks()
+                    .iter()
+                    .map(|entry| (entry.task.id.clone(), entry.state.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
 
-            {
-                let mut running = self.running_agent.lock();
-                *running = Some(agent_id.clone());
-            } // Drop running lock
+    /// Id of the task the agent was started for, if the dispatcher started it.
+    pub fn task_of_agent(&self, session_id: &str, agent_id: &str) -> Option<String> {
+        let schedules = self.schedules.lock();
+        Some(schedules.get(session_id)?.task_run_by(agent_id)?.task.id.clone())
+    }
 
-        // Construct the dynamic prompt content for the agent
-        let prompt_content = format!(
-            r#"You are a Vibe agent named {}. Your task is to {}.
+    /// Returns a copy of a session's task DAG for snapshotting.
+    pub fn schedule_snapshot(&self, session_id: &str) -> Option<TaskSchedule> {
+        self.schedules.lock().get(session_id).cloned()
+    }
+
+    /// Reinstates a session's task DAG loaded from a snapshot. Tasks that were
+    /// running keep their worker slot until their agent reaches a final status.
+    pub fn restore_schedule(&self, session_id: &str, schedule: TaskSchedule) {
+        let mut running = self.running_agents.lock();
+        for entry in schedule.tasks() {
+            if let TaskState::Running { agent_id } = &entry.state {
+                running.insert(agent_id.clone(), session_id.to_string());
+            }
+        }
+        self.schedules.lock().insert(session_id.to_string(), schedule);
+    }
+
+    /// Starts any tasks that became ready while the server was not scheduling,
+    /// e.g. after restoring snapshots at startup.
+    pub async fn resume(&self) {
+        self.process_queue().await;
+    }
+
+    /// Answers interactions whose deadline passed with their default, so the
+    /// agents waiting on them count as running again.
+    pub fn expire_interactions(&self) {
+        for agent in self.registry.expire_interactions(Utc::now()) {
+            info!("Interaction of agent {} expired", agent.id);
+            self.registry.events().publish(AgentEvent::StatusChanged {
+                agent_id: agent.id,
+                session_id: agent.session_id,
+                status: agent.status,
+            });
+        }
+    }
 
-You have access to the following Vibe utilities, which are executable binaries in your PATH:
-- `vibe-report --agent-id {} --session-id {} --progress <percentage> --thought "<message>"`
-- `vibe-ask --agent-id {} --session-id {} --question "<question>"` (Blocks until user replies)
-- `vibe-complete --agent-id {} --session-id {} --result "<summary>"`
+    /// Kills workers that ran past their wall-clock limit or went quiet for longer
+    /// than the idle limit, marks them failed and refills the freed slots. Also
+    /// integrates finished workers whose exit was missed.
+    pub async fn enforce_timeouts(&self) {
+        self.expire_interactions();
+        self.integrate_pending();
+        let timed_out = self.timed_out_agents(Utc::now());
+        if timed_out.is_empty() {
+            return;
+        }
+        for (agent_id, reason) in timed_out {
+            warn!("Agent {} timed out: {}", agent_id, reason);
+            self.fail_timed_out(&agent_id);
+        }
+        self.process_queue().await;
+    }
 
-**IMPORTANT:** To use these utilities, you MUST use the `run_shell_command` tool. 
-For example: `run_shell_command(command="vibe-complete --agent-id ...")`
+    /// Returns the in-flight agents that exceeded a limit at `now`, with the reason.
+    fn timed_out_agents(&self, now: DateTime<Utc>) -> Vec<(String, String)> {
+        let schedules = self.schedules.lock();
+        let running = self.running_agents.lock();
 
-Do NOT try to call these as direct tool functions.
+        running
+            .iter()
+            .filter_map(|(agent_id, session_id)| {
+                let agent = self.registry.get_agent(agent_id)?;
+                if !agent.status.is_active() {
+                    return None;
+                }
 
-You should use your tools to perform the task. When you believe you have successfully completed the task, use `vibe-complete`.
+                let wall_limit = schedules
+                    .get(session_id)
+                    .and_then(|schedule| schedule.task_for_agent(agent_id))
+                    .and_then(|task| task.timeout_secs)
+                    .unwrap_or(self.config.agent_timeout_secs);
+                let running_for = agent.running_secs(now);
+                if wall_limit > 0 && running_for > wall_limit as i64 {
+                    return Some((agent_id.clone(), format!("ran for {}s, limit is {}s", running_for, wall_limit)));
+                }
 
-Task: {}
-"#,
-            agent_id, task.description, agent_id, session_id, agent_id, session_id, agent_id, session_id, task.description
-        );
+                // Waiting on the user or paused by them is not a hang
+                let idle_limit = self.config.agent_idle_timeout_secs;
+                let idle_for = (now - agent.last_activity_at).num_seconds();
+                let can_idle = !matches!(agent.status, AgentStatus::WaitingForInteraction | AgentStatus::Paused);
+                if idle_limit > 0 && can_idle && idle_for > idle_limit as i64 {
+                    return Some((agent_id.clone(), format!("idle for {}s, limit is {}s", idle_for, idle_limit)));
+                }
+                None
+            })
+            .collect()
+    }
+
+    /// Kills a timed-out worker. It counts as failed, never as terminated, so
+    /// its task keeps the attempts the retry policy allows.
+    fn fail_timed_out(&self, agent_id: &str) {
+        let killed = match self.spawner.fail_agent(agent_id, "timeout".to_string()) {
+            Ok(_) => true,
+            Err(AgentControlError::NoProcess(_)) => false,
+            Err(e) => {
+                error!("Failed to kill timed-out agent {}: {}", agent_id, e);
+                false
+            }
+        };
+        let session_id = self.running_agents.lock().remove(agent_id);
+        if killed {
+            // The spawner recorded and published the failure
+            return;
+        }
+        let status = AgentStatus::Failed("timeout".to_string());
+        if let Err(e) = self.registry.update_status(agent_id, status.clone()) {
+            error!("Failed to mark agent {} as timed out: {}", agent_id, e);
+        }
+        if let Some(session_id) = session_id {
+            self.registry.events().publish(AgentEvent::StatusChanged {
+                agent_id: agent_id.to_string(),
+                session_id,
+                status,
+            });
+        }
+    }
+
+    /// Fills free worker slots until the limits are reached or no task is ready.
+    async fn process_queue(&self) {
+        for (session_id, task_id, attempts, reason) in self.refresh_schedules() {
+            error!("Task {} in session {} failed after {} attempt(s): {}", task_id, session_id, attempts, reason);
+            if let Some(sessions) = &self.sessions {
+                sessions.publish(&session_id, WsEvent::TaskFailed {
+                    session_id: session_id.clone(),
+                    task_id,
+                    attempts,
+                    reason,
+                }).await;
+            }
+        }
+        while let Some((session_id, task, agent_id)) = self.claim_next_task() {
+            self.start_task(session_id, task, agent_id).await;
+        }
+    }
+
+    /// Syncs every schedule with its agents. Returns the tasks that failed for
+    /// good as (session_id, task_id, attempts, reason).
+    fn refresh_schedules(&self) -> Vec<(String, String, u32, String)> {
+        let mut schedules = self.schedules.lock();
+        let mut failures = Vec::new();
+        for (session_id, schedule) in schedules.iter_mut() {
+            let outcome = schedule.refresh(|agent_id| {
+                self.registry.get_agent(agent_id).map(|agent| match agent.status {
+                    // Not done until the hub has checked the work
+                    AgentStatus::Completed if !agent.work_checked => AgentStatus::Running,
+                    status => status,
+                })
+            });
+            for task_id in outcome.retried {
+                let retry_at = schedule.get(&task_id).and_then(|entry| entry.retry_at);
+                warn!("Task {} in session {} failed; retrying at {:?}", task_id, session_id, retry_at);
+            }
+            for task_id in outcome.blocked {
+                warn!("Task {} in session {} is blocked because a predecessor failed", task_id, session_id);
+            }
+            for (task_id, reason) in outcome.failed {
+                let attempts = schedule.get(&task_id).map_or(0, |entry| entry.attempts);
+                failures.push((session_id.clone(), task_id, attempts, reason));
+            }
+        }
+        failures
+    }
+
+    /// Picks the next ready task that fits within the global and per-session
+    /// worker limits, and reserves a slot for it.
+    fn claim_next_task(&self) -> Option<(String, ScheduledTask, String)> {
+        let mut schedules = self.schedules.lock();
+        let mut running = self.running_agents.lock();
+
+        // Free slots held by agents that already finished without a completion signal
+        running.retain(|agent_id, _| {
+            !matches!(
+                self.registry.get_agent(agent_id).map(|a| a.status),
+                Some(AgentStatus::Completed) | Some(AgentStatus::Failed(_)) | Some(AgentStatus::Terminated)
+            )
+        });
+
+        if running.len() >= self.config.max_workers {
+            return None;
+        }
+
+        let mut session_ids: Vec<String> = schedules.keys().cloned().collect();
+        session_ids.sort();
+
+        for session_id in session_ids {
+            let schedule = schedules.get_mut(&session_id)?;
+            let in_session = running.values().filter(|s| **s == session_id).count();
+            if in_session >= self.config.max_workers_per_session {
+                continue;
+            }
+            if let Some(task_id) = schedule.next_ready().map(|task| task.id.clone()) {
+                let agent_id = uuid::Uuid::new_v4().to_string();
+                schedule.start(&task_id, &agent_id);
+                running.insert(agent_id.clone(), session_id.clone());
+                let entry = schedule.get(&task_id)?.clone();
+                return Some((session_id, entry, agent_id));
+            }
+        }
+        None
+    }
+
+    async fn start_task(&self, session_id: String, entry: ScheduledTask, agent_id: String) {
+        let task = entry.task;
+        info!("Starting execution for task: {} - {} (attempt {})", task.id, task.description, entry.attempts);
+
+        let agent_type = task.agent_type.clone().unwrap_or_else(|| "worker".to_string());
+
+        // Construct the dynamic prompt content for the agent
+        let mut previous_results = None;
+        if let Some(failure) = &entry.last_failure {
+            let log_path = self.base_dir
+                .join(".vibe")
+                .join("agents")
+                .join(&session_id)
+                .join(&failure.agent_id)
+                .join("debug_log.txt");
+            let log_tail = debug_log_tail(&log_path, FAILED_ATTEMPT_LOG_LINES).await;
+            previous_results = Some(format!(
+                "\n## Previous attempt\n\nAttempt {} of this task failed: {}\nThe last lines of its debug log were:\n\n```\n{}\n```\n",
+                entry.attempts - 1, failure.reason, log_tail
+            ));
+        }
+        let profile = self.prompt_profile();
+        let session = self.project_session(&session_id);
+        let mut prompt_content = task_prompt(
+            profile.as_ref(),
+            &session_id,
+            session.as_ref(),
+            &agent_id,
+            &task,
+            previous_results.as_deref(),
+        )
+        .text;
+
+        let agent_dir = self.base_dir.join(".vibe").join("agents").join(&session_id).join(&agent_id);
+        let worktree = match self.prepare_worktree(&session_id, &agent_id, &task.id, entry.attempts).await {
+            Ok(worktree) => worktree,
+            Err(e) => {
+                error!("Failed to create worktree for task {}: {}", task.id, e);
+                self.fail_unstarted(&session_id, &agent_id, &agent_type, &task.id, format!("could not create worktree: {}", e));
+                return;
+            }
+        };
+        if let Some(worktree) = &worktree {
+            prompt_content.push_str(&format!(
+                "\n## Workspace\n\nYou are working in a git worktree of the project at `{}`, on branch `{}`.\nCommit your changes to this branch. Write `RESULT.json` to `{}`.\n",
+                worktree.path.display(),
+                worktree.branch,
+                agent_dir.display()
+            ));
+        }
 
         let mut env_vars = HashMap::new();
+        env_vars.insert("VIBE_AGENT_DIR".to_string(), agent_dir.to_string_lossy().to_string());
         if let Ok(key) = env::var("GEMINI_API_KEY") {
             env_vars.insert("GEMINI_API_KEY".to_string(), key);
         }
@@ -149,32 +800,635 @@ Task: {}
 
         let adapter = crate::llm::adapters::get_adapter(&self.config.default_llm.provider);
         let command = adapter.get_command();
+        env_vars.extend(adapter.get_env());
+        // Outside the agent directory the relative instruction path would not resolve
+        let instruction_path = if worktree.is_some() {
+            agent_dir.join("INSTRUCTION.md").to_string_lossy().to_string()
+        } else {
+            "INSTRUCTION.md".to_string()
+        };
         let args = adapter.get_args(
-            "INSTRUCTION.md", 
+            &instruction_path,
             &self.config.default_llm.model
         );
 
-                    // Await the async spawn_agent call
-                    match self.spawner.spawn_agent(
-                        session_id.clone(),
-                        agent_type,
-                        prompt_content, 
-                        command.clone(),
-                        args.clone(),
-                        env_vars.clone(),
-                        Some(agent_id.clone()), // Pass the agent ID we generated
-                    ).await { 
-                        Ok(spawned_agent_id) => {
-                            info!("Successfully spawned agent {} for task {}. Worker will read INSTRUCTION.md.", spawned_agent_id, task.id);
-                        },
-                        Err(e) => {
-                            error!("Failed to spawn agent for task {}: {}", task.id, e);
-                            // If spawn fails, we should probably clear the running flag so the next task can try
-                            let mut running = self.running_agent.lock();
-                            *running = None;
-                            // Optionally re-trigger process_queue logic?
-                        }
+        // Await the async spawn_agent call
+        match self.spawner.spawn_agent(
+            session_id.clone(),
+            agent_type.clone(),
+            prompt_content, 
+            command.clone(),
+            args.clone(),
+            env_vars.clone(),
+            Some(agent_id.clone()), // Pass the agent ID we generated
+            worktree.as_ref().map(|w| w.path.clone()),
+        ).await { 
+            Ok(spawned_agent_id) => {
+                info!("Successfully spawned agent {} for task {}. Worker will read INSTRUCTION.md.", spawned_agent_id, task.id);
+                if let Err(e) = self.registry.set_lineage(&spawned_agent_id, entry.requested_by.clone(), Some(task.id.clone())) {
+                    error!("Failed to record parent of agent {}: {}", spawned_agent_id, e);
+                }
+                if let Some(worktree) = worktree {
+                    if let Err(e) = self.registry.set_worktree(&spawned_agent_id, worktree) {
+                        error!("Failed to record worktree for agent {}: {}", spawned_agent_id, e);
+                    }
+                }
+            },
+            Err(e) => {
+                error!("Failed to spawn agent for task {}: {}", task.id, e);
+                if let Some(worktree) = worktree {
+                    if let Err(e) = blocking(move || worktree::remove_worktree(&worktree)).await {
+                        warn!("Failed to remove worktree of unstarted agent {}: {}", agent_id, e);
                     }
                 }
+                self.fail_unstarted(&session_id, &agent_id, &agent_type, &task.id, e);
             }
         }
+    }
+
+    /// Records a worker that could not be started as a failed attempt, so its
+    /// task goes through the retry policy, and releases its slot.
+    fn fail_unstarted(&self, session_id: &str, agent_id: &str, agent_type: &str, task_id: &str, reason: String) {
+        // The spawner registers the agents whose process failed to start itself
+        if self.registry.get_agent(agent_id).is_none() {
+            let mut agent = Agent::new(session_id.to_string(), agent_type.to_string());
+            agent.id = agent_id.to_string();
+            agent.status = AgentStatus::Failed(reason.clone());
+            agent.task_id = Some(task_id.to_string());
+            self.registry.register_agent(agent);
+            self.registry.events().publish(AgentEvent::Failed {
+                agent_id: agent_id.to_string(),
+                session_id: session_id.to_string(),
+                reason,
+            });
+        }
+        self.running_agents.lock().remove(agent_id);
+    }
+
+    /// Creates the worker's worktree when worktree isolation is enabled and the
+    /// session's project root is known.
+    async fn prepare_worktree(
+        &self,
+        session_id: &str,
+        agent_id: &str,
+        task_id: &str,
+        attempt: u32,
+    ) -> Result<Option<WorktreeInfo>, worktree::WorktreeError> {
+        if self.config.worker_isolation != WorkerIsolation::Worktree {
+            return Ok(None);
+        }
+        let Some(project_root) = self.project_session(session_id).map(|s| PathBuf::from(s.project_root)) else {
+            warn!("No project root known for session {}; running task {} without a worktree", session_id, task_id);
+            return Ok(None);
+        };
+
+        let path = self.worktrees_dir(session_id).join(agent_id);
+        let branch = worktree::worker_branch(session_id, task_id, attempt);
+        let info = blocking(move || worktree::create_worktree(&project_root, &path, &branch)).await?;
+        info!("Created worktree {:?} on branch {} for task {}", info.path, info.branch, task_id);
+        Ok(Some(info))
+    }
+
+    /// Whether a task of the agent's session is running under it.
+    fn holds_task(&self, agent: &Agent) -> bool {
+        self.schedules
+            .lock()
+            .get(&agent.session_id)
+            .and_then(|schedule| schedule.task_run_by(&agent.id))
+            .is_some_and(|entry| matches!(entry.state, TaskState::Running { .. }))
+    }
+
+    fn project_session(&self, session_id: &str) -> Option<ProjectSession> {
+        self.project_sessions.as_ref()?.read().get(session_id).cloned()
+    }
+
+    fn prompt_profile(&self) -> Option<PromptProfile> {
+        let id = self.config.prompt_profile.as_deref()?;
+        self.profiles.as_ref()?.get(id)
+    }
+
+    fn worktrees_dir(&self, session_id: &str) -> PathBuf {
+        self.base_dir.join(".vibe").join("worktrees").join(session_id)
+    }
+
+    /// Records what a finished worker changed in its worktree on the agent and
+    /// in its result.
+    async fn record_worktree_diff(&self, agent_id: &str) {
+        let Some(mut info) = self.registry.get_agent(agent_id).and_then(|agent| agent.worktree) else {
+            return;
+        };
+        if info.diff_stat.is_some() {
+            return;
+        }
+        let stat = blocking({
+            let info = info.clone();
+            move || worktree::diff_stat(&info)
+        });
+        match stat.await {
+            Ok(stat) => {
+                info.diff_stat = Some(stat);
+                let summary = info.summary();
+                if let Err(e) = self
+                    .registry
+                    .set_worktree(agent_id, info)
+                    .and_then(|_| self.registry.append_result(agent_id, &summary))
+                {
+                    error!("Failed to record diff for agent {}: {}", agent_id, e);
+                }
+            }
+            Err(e) => error!("Failed to compute diff for agent {}: {}", agent_id, e),
+        }
+    }
+
+}
+
+/// Returns the last `lines` lines of an agent's debug log.
+async fn debug_log_tail(path: &std::path::Path, lines: usize) -> String {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => {
+            let all: Vec<&str> = content.lines().collect();
+            all[all.len().saturating_sub(lines)..].join("\n")
+        }
+        Err(e) => format!("(debug log unavailable: {})", e),
+    }
+}
+
+
+async fn process_running(agent: &Agent) -> bool {
+    match agent.pid {
+        Some(pgid) => blocking(move || process_group_alive(pgid)).await,
+        None => false,
+    }
+}
+
+/// Runs blocking work, such as git commands, off the runtime's worker threads.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(work).await {
+        Ok(value) => value,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LlmConfig, ProviderKind};
+    use std::net::IpAddr;
+    use tempfile::{tempdir, TempDir};
+
+    /// A dispatcher rooted in a fresh directory, which is removed once the
+    /// returned `TempDir` is dropped.
+    fn test_dispatcher(max_workers: usize, max_workers_per_session: usize) -> (TaskDispatcher, TempDir) {
+        let registry = AgentRegistry::new();
+        let base_temp = tempdir().unwrap();
+        let base_dir = base_temp.path().to_path_buf();
+        let config = ServerConfig {
+            host: "127.0.0.1".parse::<IpAddr>().unwrap(),
+            http_port: 4110,
+            ws_port: 4111,
+            shared_secret: None,
+            prompt_profile_dir: base_dir.clone(),
+            prompt_profile: None,
+            default_llm: LlmConfig {
+                provider: ProviderKind::Dummy,
+                model: "dummy".into(),
+                temperature: 0.2,
+            },
+            max_workers,
+            max_workers_per_session,
+            price_table: Default::default(),
+            agent_timeout_secs: 3600,
+            agent_idle_timeout_secs: 600,
+            task_max_attempts: 2,
+            task_retry_backoff_secs: 30,
+            worker_isolation: Default::default(),
+            merge_mode: Default::default(),
+            merge_conflict_policy: Default::default(),
+            boundary_policy: Default::default(),
+        };
+        let dispatcher = TaskDispatcher::new(
+            AgentSpawner::new(registry.clone(), base_dir.clone()),
+            registry,
+            Arc::new(config),
+            base_dir,
+        );
+        (dispatcher, base_temp)
+    }
+
+    fn graph(ids: &[&str]) -> TaskGraph {
+        TaskGraph {
+            tasks: ids
+                .iter()
+                .map(|id| Task::new(id.to_string(), format!("Do {}", id)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_claim_respects_worker_limits() {
+        let (dispatcher, _base_dir) = test_dispatcher(3, 2);
+        {
+            let mut schedules = dispatcher.schedules.lock();
+            schedules.entry("session-a".to_string()).or_default().merge(graph(&["a1", "a2", "a3"])).unwrap();
+            schedules.entry("session-b".to_string()).or_default().merge(graph(&["b1", "b2"])).unwrap();
+        }
+
+        let mut claimed = Vec::new();
+        while let Some((session_id, task, _)) = dispatcher.claim_next_task() {
+            claimed.push((session_id, task.task.id));
+        }
+
+        assert_eq!(claimed.len(), 3);
+        assert_eq!(claimed.iter().filter(|(s, _)| s == "session-a").count(), 2);
+        assert_eq!(claimed.iter().filter(|(s, _)| s == "session-b").count(), 1);
+    }
+
+    #[test]
+    fn test_completion_frees_a_slot() {
+        let (dispatcher, _base_dir) = test_dispatcher(1, 1);
+        dispatcher.schedules.lock().entry("session-a".to_string()).or_default().merge(graph(&["a1", "a2"])).unwrap();
+
+        let (_, first, agent_id) = dispatcher.claim_next_task().expect("first task is ready");
+        assert_eq!(first.task.id, "a1");
+        assert!(dispatcher.claim_next_task().is_none());
+
+        dispatcher.running_agents.lock().remove(&agent_id);
+        let (_, second, _) = dispatcher.claim_next_task().expect("slot was freed");
+        assert_eq!(second.task.id, "a2");
+    }
+
+    #[test]
+    fn test_idle_and_overdue_agents_time_out() {
+        let (dispatcher, _base_dir) = test_dispatcher(2, 2);
+        let mut tasks = graph(&["quiet", "overdue"]);
+        tasks.tasks[1].timeout_secs = Some(60);
+        dispatcher.schedules.lock().entry("session-a".to_string()).or_default().merge(tasks).unwrap();
+
+        let now = Utc::now();
+        let mut agent_ids = Vec::new();
+        while let Some((session_id, task, agent_id)) = dispatcher.claim_next_task() {
+            let mut agent = crate::agents::registry::Agent::new(session_id, "worker".to_string());
+            agent.id = agent_id.clone();
+            agent.status = AgentStatus::Running;
+            if task.task.id == "quiet" {
+                agent.last_activity_at = now - chrono::Duration::minutes(20);
+            } else {
+                agent.started_at = now - chrono::Duration::minutes(2);
+            }
+            dispatcher.registry.register_agent(agent);
+            agent_ids.push(agent_id);
+        }
+        assert_eq!(agent_ids.len(), 2);
+
+        let mut timed_out: Vec<String> = dispatcher.timed_out_agents(now).into_iter().map(|(id, _)| id).collect();
+        timed_out.sort();
+        agent_ids.sort();
+        assert_eq!(timed_out, agent_ids);
+
+        // An agent waiting on the user is not idle
+        dispatcher.registry.update_status(&agent_ids[0], AgentStatus::WaitingForInteraction).unwrap();
+        dispatcher.registry.update_status(&agent_ids[1], AgentStatus::WaitingForInteraction).unwrap();
+        let still_timed_out = dispatcher.timed_out_agents(now);
+        assert_eq!(still_timed_out.len(), 1);
+
+        dispatcher.fail_timed_out(&still_timed_out[0].0);
+        let agent = dispatcher.registry.get_agent(&still_timed_out[0].0).unwrap();
+        assert_eq!(agent.status, AgentStatus::Failed("timeout".to_string()));
+        assert!(!dispatcher.running_agents.lock().contains_key(&agent.id));
+    }
+
+    #[test]
+    fn test_unstarted_worker_is_retried() {
+        let (dispatcher, _base_dir) = test_dispatcher(1, 1);
+        let policy = dispatcher.config.retry_policy();
+        dispatcher
+            .schedules
+            .lock()
+            .entry("session-a".to_string())
+            .or_insert_with(|| TaskSchedule::with_retry_policy(policy))
+            .merge(graph(&["a1"]))
+            .unwrap();
+        let (session_id, task, agent_id) = dispatcher.claim_next_task().unwrap();
+
+        dispatcher.fail_unstarted(&session_id, &agent_id, "worker", &task.task.id, "could not create worktree".to_string());
+        assert!(dispatcher.running_agents.lock().is_empty());
+        assert!(dispatcher.refresh_schedules().is_empty());
+        let schedule = dispatcher.schedule_snapshot("session-a").unwrap();
+        let entry = schedule.get("a1").unwrap();
+        assert_eq!(entry.state, TaskState::Pending);
+        assert_eq!(entry.last_failure.as_ref().unwrap().reason, "could not create worktree");
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_task_is_retried() {
+        let (dispatcher, _base_dir) = test_dispatcher(1, 1);
+        let policy = dispatcher.config.retry_policy();
+        assert!(policy.max_attempts > 1);
+        dispatcher
+            .schedules
+            .lock()
+            .entry("session-a".to_string())
+            .or_insert_with(|| TaskSchedule::with_retry_policy(policy))
+            .merge(graph(&["a1"]))
+            .unwrap();
+        let (session_id, _, agent_id) = dispatcher.claim_next_task().unwrap();
+        let mut child = tokio::process::Command::new("sleep").arg("30").process_group(0).spawn().unwrap();
+        let mut agent = Agent::new(session_id, "worker".to_string());
+        agent.id = agent_id.clone();
+        agent.status = AgentStatus::Running;
+        agent.pid = child.id();
+        dispatcher.registry.register_agent(agent);
+
+        dispatcher.fail_timed_out(&agent_id);
+        child.wait().await.unwrap();
+        assert_eq!(dispatcher.registry.get_agent(&agent_id).unwrap().status, AgentStatus::Failed("timeout".to_string()));
+        assert!(dispatcher.refresh_schedules().is_empty());
+        let schedule = dispatcher.schedule_snapshot("session-a").unwrap();
+        let entry = schedule.get("a1").unwrap();
+        assert_eq!(entry.state, TaskState::Pending);
+        assert_eq!(entry.last_failure.as_ref().unwrap().reason, "timeout");
+    }
+
+    #[test]
+    fn test_paused_time_does_not_count_against_wall_limit() {
+        let (dispatcher, _base_dir) = test_dispatcher(1, 1);
+        let mut tasks = graph(&["slow"]);
+        tasks.tasks[0].timeout_secs = Some(60);
+        dispatcher.schedules.lock().entry("session-a".to_string()).or_default().merge(tasks).unwrap();
+        let (session_id, _, agent_id) = dispatcher.claim_next_task().unwrap();
+
+        let now = Utc::now();
+        let mut agent = Agent::new(session_id, "worker".to_string());
+        agent.id = agent_id.clone();
+        agent.status = AgentStatus::Running;
+        agent.started_at = now - chrono::Duration::minutes(2);
+        agent.paused_secs = 90;
+        dispatcher.registry.register_agent(agent);
+        assert!(dispatcher.timed_out_agents(now).is_empty());
+
+        dispatcher.registry.update_status(&agent_id, AgentStatus::Paused).unwrap();
+        let later = now + chrono::Duration::minutes(10);
+        assert!(dispatcher.timed_out_agents(later).is_empty());
+        assert_eq!(dispatcher.registry.get_agent(&agent_id).unwrap().running_secs(now), 30);
+    }
+
+    #[tokio::test]
+    async fn test_debug_log_tail_keeps_last_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("debug_log.txt");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        assert_eq!(debug_log_tail(&path, 2).await, "two\nthree");
+        assert!(debug_log_tail(&dir.path().join("missing.txt"), 2).await.contains("unavailable"));
+    }
+
+    #[tokio::test]
+    async fn test_exit_event_frees_slot() {
+        let (dispatcher, _base_dir) = test_dispatcher(1, 1);
+        dispatcher.schedules.lock().entry("session-a".to_string()).or_default().merge(graph(&["a1"])).unwrap();
+        let (_, _, agent_id) = dispatcher.claim_next_task().expect("task is ready");
+        dispatcher.watch_agent_events();
+
+        dispatcher.registry.events().publish(AgentEvent::Exited {
+            agent_id: agent_id.clone(),
+            session_id: "session-a".to_string(),
+            code: Some(0),
+            status: AgentStatus::Completed,
+        });
+        for _ in 0..50 {
+            if dispatcher.running_agents.lock().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert!(dispatcher.running_agents.lock().is_empty());
+    }
+
+    /// A dispatcher running workers in worktrees of a fresh repository that
+    /// backs `session-a`.
+    fn isolated_dispatcher(project: &std::path::Path) -> (TaskDispatcher, TempDir) {
+        let (mut dispatcher, base_dir) = test_dispatcher(1, 1);
+        let mut config = (*dispatcher.config).clone();
+        config.worker_isolation = WorkerIsolation::Worktree;
+        dispatcher.config = Arc::new(config);
+
+        worktree::tests::init_repo(project);
+        let session = ProjectSession {
+            session_id: "session-a".to_string(),
+            project_root: project.to_string_lossy().to_string(),
+            project_name: "demo".to_string(),
+            created_at: "2025-11-18T16:00:00Z".to_string(),
+            last_active_at: "2025-11-18T16:00:00Z".to_string(),
+            status: crate::project_sessions::ProjectSessionStatus::Active,
+            latest_result: None,
+            usage: Default::default(),
+            rules: Default::default(),
+            orchestrator_lost: false,
+        };
+        let dispatcher = dispatcher.with_project_sessions(Arc::new(RwLock::new(HashMap::from([(
+            "session-a".to_string(),
+            session,
+        )]))));
+        (dispatcher, base_dir)
+    }
+
+    fn finished_agent(dispatcher: &TaskDispatcher, info: WorktreeInfo) -> String {
+        let mut agent = Agent::new("session-a".to_string(), "worker".to_string());
+        agent.status = AgentStatus::Completed;
+        let agent_id = agent.id.clone();
+        dispatcher.registry.register_agent(agent);
+        dispatcher.registry.set_worktree(&agent_id, info).unwrap();
+        agent_id
+    }
+
+    #[tokio::test]
+    async fn test_worktree_isolation_records_branch_and_diff() {
+        let project = tempdir().unwrap();
+        let (dispatcher, _base_dir) = isolated_dispatcher(project.path());
+
+        let info = dispatcher
+            .prepare_worktree("session-a", "agent-1", "t1", 1)
+            .await
+            .unwrap()
+            .expect("worktree is created");
+        assert_eq!(info.branch, "vibe/session-a/t1");
+        assert!(dispatcher.prepare_worktree("session-b", "agent-2", "t2", 1).await.unwrap().is_none());
+
+        std::fs::write(info.path.join("NEW.md"), "new\n").unwrap();
+        let agent_id = finished_agent(&dispatcher, info);
+
+        dispatcher.record_worktree_diff(&agent_id).await;
+        let agent = dispatcher.registry.get_agent(&agent_id).unwrap();
+        assert!(agent.worktree.unwrap().diff_stat.unwrap().contains("NEW.md"));
+        assert!(agent.result.unwrap().contains("Branch: `vibe/session-a/t1`"));
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_integrates_missed_exits() {
+        let project = tempdir().unwrap();
+        let (dispatcher, _base_dir) = isolated_dispatcher(project.path());
+        let info = dispatcher.prepare_worktree("session-a", "agent-1", "t1", 1).await.unwrap().unwrap();
+        std::fs::write(info.path.join("NEW.md"), "new\n").unwrap();
+        let agent_id = finished_agent(&dispatcher, info);
+        {
+            let mut schedules = dispatcher.schedules.lock();
+            let schedule = schedules.entry("session-a".to_string()).or_default();
+            schedule.merge(graph(&["t1"])).unwrap();
+            schedule.start("t1", &agent_id);
+        }
+        // The task waits until the work is checked
+        dispatcher.refresh_schedules();
+        assert_eq!(
+            dispatcher.task_states("session-a"),
+            vec![("t1".to_string(), TaskState::Running { agent_id: agent_id.clone() })]
+        );
+
+        // No exit event was seen for the agent
+        dispatcher.enforce_timeouts().await;
+        for _ in 0..200 {
+            if dispatcher.registry.get_agent(&agent_id).unwrap().work_checked {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let agent = dispatcher.registry.get_agent(&agent_id).unwrap();
+        assert!(agent.work_checked);
+        assert!(agent.worktree.unwrap().diff_stat.unwrap().contains("NEW.md"));
+        dispatcher.refresh_schedules();
+        assert_eq!(dispatcher.task_states("session-a"), vec![("t1".to_string(), TaskState::Completed)]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_conflict_raises_interaction() {
+        let project = tempdir().unwrap();
+        let (dispatcher, _base_dir) = isolated_dispatcher(project.path());
+        let first = dispatcher.prepare_worktree("session-a", "agent-1", "t1", 1).await.unwrap().unwrap();
+        let second = dispatcher.prepare_worktree("session-a", "agent-2", "t2", 1).await.unwrap().unwrap();
+        std::fs::write(first.path.join("README.md"), "first\n").unwrap();
+        std::fs::write(second.path.join("README.md"), "second\n").unwrap();
+        let first_path = first.path.clone();
+        let first_id = finished_agent(&dispatcher, first);
+        let second_id = finished_agent(&dispatcher, second);
+
+        // Concurrent merges take turns on the integration worktree
+        let (report, again) = tokio::join!(dispatcher.merge_agent(&first_id), dispatcher.merge_agent(&first_id));
+        let (report, again) = (report.unwrap(), again.unwrap());
+        assert!(matches!(report.outcome, MergeOutcome::Merged { .. }));
+        assert_eq!(report.integration_branch, "vibe/session-session-a");
+        assert_eq!(again.outcome, MergeOutcome::UpToDate);
+        // Merged work no longer needs its worktree or branch
+        assert!(!first_path.exists());
+        assert!(dispatcher.registry.get_agent(&first_id).unwrap().worktree.unwrap().removed);
+
+        let report = dispatcher.merge_agent(&second_id).await.unwrap();
+        assert_eq!(report.outcome, MergeOutcome::Conflict { files: vec!["README.md".to_string()] });
+        let agent = dispatcher.registry.get_agent(&second_id).unwrap();
+        assert_eq!(agent.pending_interaction.unwrap().id, report.interaction_id.unwrap());
+        // The question does not make a finished agent look alive again
+        assert_eq!(agent.status, AgentStatus::Completed);
+        let worktree = agent.worktree.unwrap();
+        assert!(worktree.merge.is_some());
+        assert!(worktree.path.exists());
+
+        let plain = Agent::new("session-a".to_string(), "worker".to_string());
+        let plain_id = plain.id.clone();
+        dispatcher.registry.register_agent(plain);
+        assert!(matches!(dispatcher.merge_agent(&plain_id).await, Err(MergeError::NoWorktree(_))));
+    }
+
+    #[tokio::test]
+    async fn test_boundary_violation_fails_agent() {
+        let project = tempdir().unwrap();
+        let (mut dispatcher, _base_dir) = isolated_dispatcher(project.path());
+        let mut config = (*dispatcher.config).clone();
+        config.boundary_policy = BoundaryPolicy::Fail;
+        dispatcher.config = Arc::new(config);
+
+        let info = dispatcher.prepare_worktree("session-a", "agent-1", "t1", 1).await.unwrap().unwrap();
+        std::fs::write(info.path.join("README.md"), "updated\n").unwrap();
+        std::fs::write(info.path.join("stray.txt"), "stray\n").unwrap();
+        let agent_id = finished_agent(&dispatcher, info);
+        {
+            let mut tasks = graph(&["t1"]);
+            tasks.tasks[0].must_touch = vec!["README.md".to_string()];
+            let mut schedules = dispatcher.schedules.lock();
+            let schedule = schedules.entry("session-a".to_string()).or_default();
+            schedule.merge(tasks).unwrap();
+            schedule.start("t1", &agent_id);
+        }
+        dispatcher.refresh_schedules();
+
+        dispatcher.integrate_finished_work(&agent_id).await;
+        let agent = dispatcher.registry.get_agent(&agent_id).unwrap();
+        assert!(matches!(&agent.status, AgentStatus::Failed(reason) if reason.contains("stray.txt")));
+        assert!(agent.result.unwrap().contains("Boundary violation"));
+        // The task never counted as completed, so nothing depending on it started
+        let failures = dispatcher.refresh_schedules();
+        assert!(failures[0].3.contains("stray.txt"));
+        assert!(matches!(&dispatcher.task_states("session-a")[0].1, TaskState::Failed(_)));
+    }
+
+    /// Adds a `*.md` rule whose formatter check fails unless README.md says "formatted".
+    fn add_markdown_rule(dispatcher: &TaskDispatcher) {
+        if let Some(sessions) = &dispatcher.project_sessions {
+            let mut sessions = sessions.write();
+            let rules = &mut sessions.get_mut("session-a").unwrap().rules;
+            rules.file_type_overrides.insert(
+                "*.md".to_string(),
+                crate::vibe_project::FileTypeRule {
+                    instructions: None,
+                    formatter: Some("grep -q formatted README.md".to_string()),
+                    linter: Some("true".to_string()),
+                },
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_rule_check_fails_agent() {
+        let project = tempdir().unwrap();
+        let (dispatcher, _base_dir) = isolated_dispatcher(project.path());
+        add_markdown_rule(&dispatcher);
+
+        let info = dispatcher.prepare_worktree("session-a", "agent-1", "t1", 1).await.unwrap().unwrap();
+        std::fs::write(info.path.join("README.md"), "sloppy\n").unwrap();
+        let agent_id = finished_agent(&dispatcher, info);
+        {
+            let mut schedules = dispatcher.schedules.lock();
+            let schedule = schedules.entry("session-a".to_string()).or_default();
+            schedule.merge(graph(&["t1"])).unwrap();
+            schedule.start("t1", &agent_id);
+        }
+
+        dispatcher.integrate_finished_work(&agent_id).await;
+        let agent = dispatcher.registry.get_agent(&agent_id).unwrap();
+        assert!(matches!(&agent.status, AgentStatus::Failed(reason) if reason.contains("grep -q formatted")));
+        let checks = agent.rule_checks.unwrap();
+        assert_eq!(checks.iter().map(|check| check.passed).collect::<Vec<_>>(), vec![false, true]);
+        assert!(agent.result.unwrap().contains("## Rule checks"));
+    }
+
+    #[tokio::test]
+    async fn test_rule_checks_skipped_without_worktree() {
+        let project = tempdir().unwrap();
+        let (dispatcher, _base_dir) = isolated_dispatcher(project.path());
+        add_markdown_rule(&dispatcher);
+
+        let mut agent = Agent::new("session-a".to_string(), "worker".to_string());
+        agent.status = AgentStatus::Completed;
+        let agent_id = agent.id.clone();
+        dispatcher.registry.register_agent(agent);
+        {
+            let mut schedules = dispatcher.schedules.lock();
+            let schedule = schedules.entry("session-a".to_string()).or_default();
+            schedule.merge(graph(&["t1"])).unwrap();
+            schedule.start("t1", &agent_id);
+        }
+
+        dispatcher.integrate_finished_work(&agent_id).await;
+        let agent = dispatcher.registry.get_agent(&agent_id).unwrap();
+        assert_eq!(agent.status, AgentStatus::Completed);
+        assert_eq!(agent.rule_checks, Some(Vec::new()));
+        assert!(agent.result.unwrap().contains("Skipped"));
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::agents::registry::AgentStatus;
+
+/// Events buffered per subscriber before slow receivers start lagging.
+const AGENT_EVENT_CAPACITY: usize = 1024;
+
+/// Output lines buffered per subscriber; a lagging viewer can backfill from the log.
+const AGENT_OUTPUT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Something that happened to an agent, published by whichever part of the
+/// server observed it (spawner, result watcher or the shim endpoints).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AgentEvent {
+    Spawned {
+        agent_id: String,
+        session_id: String,
+        pid: Option<u32>,
+    },
+    Reported {
+        agent_id: String,
+        session_id: String,
+        progress: u8,
+        thought: Option<String>,
+    },
+    Asked {
+        agent_id: String,
+        session_id: String,
+        interaction_id: String,
+        question: String,
+    },
+    Completed {
+        agent_id: String,
+        session_id: String,
+        result: Option<String>,
+    },
+    /// The process exited; `status` is what the registry recorded for it.
+    Exited {
+        agent_id: String,
+        session_id: String,
+        code: Option<i32>,
+        status: AgentStatus,
+    },
+    Failed {
+        agent_id: String,
+        session_id: String,
+        reason: String,
+    },
+    /// Status set from outside the agent, e.g. kill, pause or a timeout.
+    StatusChanged {
+        agent_id: String,
+        session_id: String,
+        status: AgentStatus,
+    },
+}
+
+impl AgentEvent {
+    pub fn agent_id(&self) -> &str {
+        match self {
+            AgentEvent::Spawned { agent_id, .. }
+            | AgentEvent::Reported { agent_id, .. }
+            | AgentEvent::Asked { agent_id, .. }
+            | AgentEvent::Completed { agent_id, .. }
+            | AgentEvent::Exited { agent_id, .. }
+            | AgentEvent::Failed { agent_id, .. }
+            | AgentEvent::StatusChanged { agent_id, .. } => agent_id,
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        match self {
+            AgentEvent::Spawned { session_id, .. }
+            | AgentEvent::Reported { session_id, .. }
+            | AgentEvent::Asked { session_id, .. }
+            | AgentEvent::Completed { session_id, .. }
+            | AgentEvent::Exited { session_id, .. }
+            | AgentEvent::Failed { session_id, .. }
+            | AgentEvent::StatusChanged { session_id, .. } => session_id,
+        }
+    }
+
+    /// Whether the agent no longer holds a worker slot after this event.
+    pub fn is_final(&self) -> bool {
+        match self {
+            AgentEvent::Completed { .. } | AgentEvent::Exited { .. } | AgentEvent::Failed { .. } => true,
+            AgentEvent::StatusChanged { status, .. } => !status.is_active(),
+            _ => false,
+        }
+    }
+}
+
+/// A line an agent wrote to stdout or stderr.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentOutput {
+    pub agent_id: String,
+    pub session_id: String,
+    pub stream: OutputStream,
+    pub seq: u64, // Line number across both streams, matching the debug log
+    pub line: String,
+}
+
+/// Fan-out channels for agent lifecycle events and output lines. Output has
+/// a channel of its own, so a chatty agent cannot make lifecycle subscribers
+/// such as the dispatcher lag.
+#[derive(Clone)]
+pub struct AgentEventBus {
+    sender: broadcast::Sender<AgentEvent>,
+    output: broadcast::Sender<AgentOutput>,
+}
+
+impl AgentEventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(AGENT_EVENT_CAPACITY);
+        let (output, _) = broadcast::channel(AGENT_OUTPUT_CAPACITY);
+        Self { sender, output }
+    }
+
+    /// Publishes an event; it is dropped if nobody is subscribed.
+    pub fn publish(&self, event: AgentEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<AgentEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Publishes an output line; it is dropped if nobody is subscribed.
+    pub fn publish_output(&self, output: AgentOutput) {
+        let _ = self.output.send(output);
+    }
+
+    pub fn subscribe_output(&self) -> broadcast::Receiver<AgentOutput> {
+        self.output.subscribe()
+    }
+}
+
+impl Default for AgentEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_receive_published_events() {
+        let bus = AgentEventBus::new();
+        bus.publish(AgentEvent::Failed {
+            agent_id: "dropped".to_string(),
+            session_id: "s".to_string(),
+            reason: "nobody listening".to_string(),
+        });
+
+        let mut receiver = bus.subscribe();
+        let event = AgentEvent::Exited {
+            agent_id: "a".to_string(),
+            session_id: "s".to_string(),
+            code: Some(1),
+            status: AgentStatus::Failed("Exited with status: 1".to_string()),
+        };
+        bus.publish(event.clone());
+
+        let received = receiver.recv().await.unwrap();
+        assert_eq!(received, event);
+        assert!(received.is_final());
+        assert_eq!(received.agent_id(), "a");
+    }
+
+    #[tokio::test]
+    async fn test_output_does_not_crowd_out_lifecycle_events() {
+        let bus = AgentEventBus::new();
+        let mut events = bus.subscribe();
+        let mut output = bus.subscribe_output();
+        for seq in 0..(AGENT_EVENT_CAPACITY as u64 * 2) {
+            bus.publish_output(AgentOutput {
+                agent_id: "a".to_string(),
+                session_id: "s".to_string(),
+                stream: OutputStream::Stdout,
+                seq,
+                line: "building".to_string(),
+            });
+        }
+        let event = AgentEvent::Completed {
+            agent_id: "a".to_string(),
+            session_id: "s".to_string(),
+            result: None,
+        };
+        bus.publish(event.clone());
+
+        assert_eq!(events.recv().await.unwrap(), event);
+        assert!(matches!(output.recv().await, Err(broadcast::error::RecvError::Lagged(_))));
+    }
+
+    #[test]
+    fn test_status_change_is_final_only_for_stopped_agents() {
+        let change = |status| AgentEvent::StatusChanged {
+            agent_id: "a".to_string(),
+            session_id: "s".to_string(),
+            status,
+        };
+        assert!(!change(AgentStatus::Paused).is_final());
+        assert!(change(AgentStatus::Terminated).is_final());
+    }
+}
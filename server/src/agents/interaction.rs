@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// What kind of answer a `vibe-ask` question expects, so clients can render
+/// the matching control.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InteractionKind {
+    #[default]
+    FreeText,
+    /// Answered with `yes` or `no`.
+    Confirm,
+    /// Answered with one of `options`.
+    SingleChoice { options: Vec<String> },
+    /// Answered with any of `options`, one per line.
+    MultiChoice { options: Vec<String> },
+    /// Answered with `approve` or `reject` for the listed files.
+    FileApproval { files: Vec<String> },
+}
+
+impl InteractionKind {
+    /// Checks the question itself, e.g. that a choice has options.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            InteractionKind::SingleChoice { options } | InteractionKind::MultiChoice { options } if options.is_empty() => {
+                Err("a choice needs at least one option".to_string())
+            }
+            InteractionKind::FileApproval { files } if files.is_empty() => {
+                Err("a file approval needs at least one file".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `answer` in its canonical form, or why it does not fit the question.
+    pub fn normalize_answer(&self, answer: &str) -> Result<String, String> {
+        let answer = answer.trim();
+        match self {
+            InteractionKind::FreeText => Ok(answer.to_string()),
+            InteractionKind::Confirm => match answer.to_lowercase().as_str() {
+                "yes" | "y" | "true" => Ok("yes".to_string()),
+                "no" | "n" | "false" => Ok("no".to_string()),
+                _ => Err(format!("expected yes or no, got '{}'", answer)),
+            },
+            InteractionKind::SingleChoice { options } => options
+                .iter()
+                .find(|option| option.as_str() == answer)
+                .cloned()
+                .ok_or_else(|| format!("'{}' is not one of: {}", answer, options.join(", "))),
+            InteractionKind::MultiChoice { options } => {
+                let mut chosen = Vec::new();
+                for choice in answer.lines().map(str::trim).filter(|choice| !choice.is_empty()) {
+                    if !options.iter().any(|option| option == choice) {
+                        return Err(format!("'{}' is not one of: {}", choice, options.join(", ")));
+                    }
+                    if !chosen.contains(&choice) {
+                        chosen.push(choice);
+                    }
+                }
+                Ok(chosen.join("\n"))
+            }
+            InteractionKind::FileApproval { .. } => match answer.to_lowercase().as_str() {
+                "approve" | "approved" | "yes" => Ok("approve".to_string()),
+                "reject" | "rejected" | "no" => Ok("reject".to_string()),
+                _ => Err(format!("expected approve or reject, got '{}'", answer)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_answers_per_kind() {
+        assert_eq!(InteractionKind::Confirm.normalize_answer(" Y ").unwrap(), "yes");
+        assert!(InteractionKind::Confirm.normalize_answer("maybe").is_err());
+
+        let options = vec!["npm".to_string(), "bun".to_string()];
+        let single = InteractionKind::SingleChoice { options: options.clone() };
+        assert_eq!(single.normalize_answer("bun").unwrap(), "bun");
+        assert!(single.normalize_answer("yarn").is_err());
+
+        let multi = InteractionKind::MultiChoice { options };
+        assert_eq!(multi.normalize_answer("bun\nnpm\nbun\n").unwrap(), "bun\nnpm");
+        assert!(multi.normalize_answer("npm\npnpm").is_err());
+
+        let approval = InteractionKind::FileApproval { files: vec!["Cargo.toml".to_string()] };
+        assert_eq!(approval.normalize_answer("Approved").unwrap(), "approve");
+        assert!(InteractionKind::MultiChoice { options: Vec::new() }.validate().is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::agents::worktree::{WorktreeError, WORKER_BRANCH_PREFIX};
+use crate::utils::process::spawn_and_capture_output;
+
+/// When finished worker branches are merged into the session's integration branch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeMode {
+    /// Only through `POST /agents/:id/merge`.
+    #[default]
+    Manual,
+    /// As soon as a worker completes successfully.
+    Auto,
+}
+
+impl std::str::FromStr for MergeMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "manual" | "" => Ok(MergeMode::Manual),
+            "auto" => Ok(MergeMode::Auto),
+            other => Err(format!("unknown merge mode '{}'", other)),
+        }
+    }
+}
+
+/// What happens when a worker branch does not merge cleanly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Raise an interaction on the worker so a person can resolve it.
+    #[default]
+    Ask,
+    /// Schedule a resolver agent in the same session.
+    Resolver,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "ask" | "" => Ok(ConflictPolicy::Ask),
+            "resolver" => Ok(ConflictPolicy::Resolver),
+            other => Err(format!("unknown merge conflict policy '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum MergeOutcome {
+    Merged { commit: String },
+    UpToDate,
+    Conflict { files: Vec<String> },
+}
+
+/// Result of merging one agent's branch, as returned by the merge endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeReport {
+    pub agent_id: String,
+    pub branch: String,
+    pub integration_branch: String,
+    #[serde(flatten)]
+    pub outcome: MergeOutcome,
+    pub resolver_task_id: Option<String>,
+    pub interaction_id: Option<String>,
+}
+
+impl MergeReport {
+    /// Markdown section appended to the agent's result.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("## Merge\n\n`{}` into `{}`: ", self.branch, self.integration_branch);
+        match &self.outcome {
+            MergeOutcome::Merged { commit } => summary.push_str(&format!("merged as {}.\n", commit)),
+            MergeOutcome::UpToDate => summary.push_str("already up to date.\n"),
+            MergeOutcome::Conflict { files } => {
+                summary.push_str(&format!("conflicts in {}.\n", files.join(", ")));
+                if let Some(task_id) = &self.resolver_task_id {
+                    summary.push_str(&format!("Resolver task `{}` was scheduled.\n", task_id));
+                }
+            }
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MergeError {
+    #[error("agent {0} not found")]
+    NotFound(String),
+    #[error("agent {0} did not work in a git worktree")]
+    NoWorktree(String),
+    #[error("agent {0} is still running")]
+    StillRunning(String),
+    #[error(transparent)]
+    Worktree(#[from] WorktreeError),
+}
+
+/// Branch the finished work of a session is merged into.
+pub fn integration_branch(session_id: &str) -> String {
+    format!("{}session-{}", WORKER_BRANCH_PREFIX, session_id)
+}
+
+/// Commits whatever the worker left uncommitted in its worktree. Returns
+/// whether a commit was made.
+pub fn commit_pending_changes(worktree: &Path, message: &str) -> Result<bool, WorktreeError> {
+    let path = worktree.to_string_lossy();
+    spawn_and_capture_output("git", &["-C", &path, "add", "--all"])?;
+    // `diff --quiet` exits non-zero when something is staged
+    if spawn_and_capture_output("git", &["-C", &path, "diff", "--cached", "--quiet"]).is_ok() {
+        return Ok(false);
+    }
+    spawn_and_capture_output("git", &["-C", &path, "commit", "-q", "-m", message])?;
+    Ok(true)
+}
+
+/// Checks out `branch` at `path`, creating the branch from the project's HEAD
+/// the first time. An existing checkout is reused.
+pub fn ensure_integration_worktree(project_root: &Path, path: &Path, branch: &str) -> Result<(), WorktreeError> {
+    if path.join(".git").exists() {
+        return Ok(());
+    }
+    let root = project_root.to_string_lossy();
+    if spawn_and_capture_output("git", &["-C", &root, "rev-parse", "HEAD"]).is_err() {
+        return Err(WorktreeError::NotARepository(project_root.to_path_buf()));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let target = path.to_string_lossy();
+    let branch_ref = format!("refs/heads/{}", branch);
+    if spawn_and_capture_output("git", &["-C", &root, "rev-parse", "--verify", "--quiet", &branch_ref]).is_ok() {
+        spawn_and_capture_output("git", &["-C", &root, "worktree", "add", &target, branch])?;
+    } else {
+        spawn_and_capture_output("git", &["-C", &root, "worktree", "add", "-b", branch, &target, "HEAD"])?;
+    }
+    Ok(())
+}
+
+/// Merges `branch` into the branch checked out at `integration`. A conflicting
+/// merge is aborted, leaving the integration branch as it was.
+pub fn merge_branch(integration: &Path, branch: &str) -> Result<MergeOutcome, WorktreeError> {
+    let path = integration.to_string_lossy();
+    let message = format!("Merge {}", branch);
+    match spawn_and_capture_output("git", &["-C", &path, "merge", "--no-ff", "-m", &message, branch]) {
+        Ok((stdout, _)) if stdout.contains("Already up to date") => Ok(MergeOutcome::UpToDate),
+        Ok(_) => {
+            let (commit, _) = spawn_and_capture_output("git", &["-C", &path, "rev-parse", "HEAD"])?;
+            Ok(MergeOutcome::Merged {
+                commit: commit.trim().to_string(),
+            })
+        }
+        Err(merge_error) => {
+            let (unmerged, _) =
+                spawn_and_capture_output("git", &["-C", &path, "diff", "--name-only", "--diff-filter=U"])?;
+            let files: Vec<String> = unmerged.lines().map(str::to_string).collect();
+            if files.is_empty() {
+                return Err(merge_error.into());
+            }
+            spawn_and_capture_output("git", &["-C", &path, "merge", "--abort"])?;
+            Ok(MergeOutcome::Conflict { files })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::worktree::{create_worktree, tests::init_repo};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_merge_and_conflict() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        init_repo(&repo);
+        let integration = dir.path().join("integration");
+        ensure_integration_worktree(&repo, &integration, "vibe/session-s1").unwrap();
+        ensure_integration_worktree(&repo, &integration, "vibe/session-s1").unwrap();
+
+        let first = create_worktree(&repo, &dir.path().join("a1"), "vibe/a").unwrap();
+        let second = create_worktree(&repo, &dir.path().join("a2"), "vibe/b").unwrap();
+        std::fs::write(first.path.join("README.md"), "from a\n").unwrap();
+        std::fs::write(second.path.join("README.md"), "from b\n").unwrap();
+        assert!(commit_pending_changes(&first.path, "a").unwrap());
+        assert!(commit_pending_changes(&second.path, "b").unwrap());
+        assert!(!commit_pending_changes(&second.path, "nothing").unwrap());
+
+        assert!(matches!(merge_branch(&integration, "vibe/a").unwrap(), MergeOutcome::Merged { .. }));
+        assert_eq!(merge_branch(&integration, "vibe/a").unwrap(), MergeOutcome::UpToDate);
+        assert_eq!(
+            merge_branch(&integration, "vibe/b").unwrap(),
+            MergeOutcome::Conflict {
+                files: vec!["README.md".to_string()]
+            }
+        );
+        // The aborted merge leaves the first worker's change in place
+        let readme = std::fs::read_to_string(integration.join("README.md")).unwrap();
+        assert_eq!(readme, "from a\n");
+    }
+}
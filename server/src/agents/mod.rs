@@ -1,4 +1,12 @@
+pub mod events;
+pub mod interaction;
+pub mod output;
 pub mod registry;
+pub mod result;
 pub mod spawner;
 pub mod watcher;
+pub mod worktree;
+pub mod merge;
+pub mod boundaries;
+pub mod checks;
 pub mod dispatcher;
\ No newline at end of file
@@ -0,0 +1,193 @@
+use std::io::{self, SeekFrom};
+use std::path::Path;
+
+use serde::Serialize;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+
+use crate::agents::events::OutputStream;
+
+/// File in the agent's directory that receives every line of its output.
+pub const DEBUG_LOG_FILE: &str = "debug_log.txt";
+
+/// Index next to the debug log: the byte offset of every
+/// `OUTPUT_INDEX_INTERVAL`th line, as little-endian `u64`s.
+pub const OUTPUT_INDEX_FILE: &str = "debug_log.idx";
+
+const OUTPUT_INDEX_INTERVAL: u64 = 256;
+
+/// One line of an agent's output. `seq` counts lines from 0 across both
+/// streams, so it is also the line's position in the debug log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutputLine {
+    pub seq: u64,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+/// Formats a line the way it is written to the debug log.
+pub fn log_entry(agent_id: &str, stream: OutputStream, line: &str) -> String {
+    let label = match stream {
+        OutputStream::Stdout => "STDOUT",
+        OutputStream::Stderr => "STDERR",
+    };
+    format!("[{}] {}: {}", agent_id, label, line)
+}
+
+/// Appends an agent's output to its debug log and keeps the log's index.
+pub struct OutputLog {
+    agent_id: String,
+    log: File,
+    index: File,
+    len: u64,
+    next_seq: u64,
+}
+
+impl OutputLog {
+    /// Creates the debug log at `path` and its index, truncating earlier ones.
+    pub async fn create(path: &Path, agent_id: &str) -> io::Result<Self> {
+        Ok(Self {
+            agent_id: agent_id.to_string(),
+            log: File::create(path).await?,
+            index: File::create(path.with_file_name(OUTPUT_INDEX_FILE)).await?,
+            len: 0,
+            next_seq: 0,
+        })
+    }
+
+    /// Writes one line and returns its `seq`.
+    pub async fn append(&mut self, stream: OutputStream, line: &str) -> io::Result<u64> {
+        let seq = self.next_seq;
+        if seq.is_multiple_of(OUTPUT_INDEX_INTERVAL) {
+            self.index.write_all(&self.len.to_le_bytes()).await?;
+        }
+        let mut entry = log_entry(&self.agent_id, stream, line);
+        entry.push('\n');
+        self.log.write_all(entry.as_bytes()).await?;
+        self.len += entry.len() as u64;
+        self.next_seq += 1;
+        Ok(seq)
+    }
+}
+
+/// Reads the output lines from `from` on back out of a debug log, starting at
+/// the closest indexed line before `from`. A missing log means the agent has
+/// not produced output yet.
+pub async fn read_output(path: &Path, agent_id: &str, from: u64) -> io::Result<Vec<OutputLine>> {
+    let mut file = match File::open(path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let (mut seq, offset) = indexed_position(&path.with_file_name(OUTPUT_INDEX_FILE), from).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    let prefix = format!("[{}] ", agent_id);
+    let mut reader = BufReader::new(file);
+    let mut raw = Vec::new();
+    let mut lines = Vec::new();
+    loop {
+        raw.clear();
+        // A final line without its newline is still being written
+        if reader.read_until(b'\n', &mut raw).await? == 0 || raw.last() != Some(&b'\n') {
+            break;
+        }
+        if seq >= from {
+            let entry = String::from_utf8_lossy(&raw);
+            let entry = entry.trim_end_matches(['\r', '\n']);
+            let entry = entry.strip_prefix(&prefix).unwrap_or(entry);
+            let (stream, line) = if let Some(line) = entry.strip_prefix("STDERR: ") {
+                (OutputStream::Stderr, line)
+            } else {
+                (OutputStream::Stdout, entry.strip_prefix("STDOUT: ").unwrap_or(entry))
+            };
+            lines.push(OutputLine {
+                seq,
+                stream,
+                line: line.to_string(),
+            });
+        }
+        seq += 1;
+    }
+    Ok(lines)
+}
+
+/// The last indexed `(seq, byte offset)` at or before `from`; the start of
+/// the log when there is no index.
+async fn indexed_position(index_path: &Path, from: u64) -> io::Result<(u64, u64)> {
+    let mut index = match File::open(index_path).await {
+        Ok(index) => index,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(e),
+    };
+    let entries = index.metadata().await?.len() / 8;
+    if entries == 0 {
+        return Ok((0, 0));
+    }
+    let entry = (from / OUTPUT_INDEX_INTERVAL).min(entries - 1);
+    index.seek(SeekFrom::Start(entry * 8)).await?;
+    let mut offset = [0; 8];
+    index.read_exact(&mut offset).await?;
+    Ok((entry * OUTPUT_INDEX_INTERVAL, u64::from_le_bytes(offset)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_read_output_from_seq() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(DEBUG_LOG_FILE);
+        assert!(read_output(&path, "a1", 0).await.unwrap().is_empty());
+
+        let entries = [
+            log_entry("a1", OutputStream::Stdout, "building"),
+            log_entry("a1", OutputStream::Stderr, "warning: unused"),
+            log_entry("a1", OutputStream::Stdout, "done"),
+        ];
+        std::fs::write(&path, entries.join("\n") + "\n").unwrap();
+
+        let lines = read_output(&path, "a1", 1).await.unwrap();
+        assert_eq!(
+            lines,
+            vec![
+                OutputLine {
+                    seq: 1,
+                    stream: OutputStream::Stderr,
+                    line: "warning: unused".to_string()
+                },
+                OutputLine {
+                    seq: 2,
+                    stream: OutputStream::Stdout,
+                    line: "done".to_string()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_output_seeks_through_the_index() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join(DEBUG_LOG_FILE);
+        let mut log = OutputLog::create(&path, "a1").await.unwrap();
+        for n in 0..600 {
+            let stream = if n % 2 == 0 { OutputStream::Stdout } else { OutputStream::Stderr };
+            assert_eq!(log.append(stream, &format!("line {}", n)).await.unwrap(), n);
+        }
+
+        let lines = read_output(&path, "a1", 513).await.unwrap();
+        assert_eq!(lines.len(), 87);
+        assert_eq!(
+            lines[0],
+            OutputLine {
+                seq: 513,
+                stream: OutputStream::Stderr,
+                line: "line 513".to_string()
+            }
+        );
+        assert_eq!(read_output(&path, "a1", 0).await.unwrap().len(), 600);
+        assert!(read_output(&path, "a1", 900).await.unwrap().is_empty());
+    }
+}
@@ -1,10 +1,19 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 use serde::{Serialize, Deserialize};
 
+use crate::agents::events::AgentEventBus;
+use crate::agents::checks::RuleCheck;
+use crate::agents::interaction::InteractionKind;
+use crate::agents::worktree::WorktreeInfo;
+use crate::usage::TokenUsage;
+
 /// Represents the status of an agent.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AgentStatus {
     Starting,
     Running,
@@ -12,12 +21,40 @@ pub enum AgentStatus {
     Failed(String),
     Terminated,
     WaitingForInteraction, // Added status
+    Paused, // Process group stopped with SIGSTOP
+}
+
+impl AgentStatus {
+    /// Name used for this status in `WsEvent::AgentStatusUpdate`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            AgentStatus::Starting => "starting",
+            AgentStatus::Running => "running",
+            AgentStatus::Completed => "completed",
+            AgentStatus::Failed(_) => "failed",
+            AgentStatus::Terminated => "terminated",
+            AgentStatus::WaitingForInteraction => "waiting_for_interaction",
+            AgentStatus::Paused => "paused",
+        }
+    }
+
+    /// Whether the agent's process is expected to still exist.
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            AgentStatus::Starting
+                | AgentStatus::Running
+                | AgentStatus::WaitingForInteraction
+                | AgentStatus::Paused
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum InteractionStatus {
     Pending,
     Resolved,
+    Expired, // The deadline passed without an answer; `result` holds the default, if any
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,20 +63,87 @@ pub struct Interaction {
     pub description: String, // The question asked
     pub result: Option<String>, // The answer provided
     pub status: InteractionStatus,
+    #[serde(default)]
+    pub kind: InteractionKind,
+    #[serde(default)]
+    pub default_answer: Option<String>,
+    #[serde(default)]
+    pub deadline: Option<DateTime<Utc>>, // After this the default answer is used
+}
+
+impl Interaction {
+    pub fn new(description: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            description,
+            result: None,
+            status: InteractionStatus::Pending,
+            kind: InteractionKind::default(),
+            default_answer: None,
+            deadline: None,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: InteractionKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_default_answer(mut self, answer: Option<String>) -> Self {
+        self.default_answer = answer;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Option<DateTime<Utc>>) -> Self {
+        self.deadline = deadline;
+        self
+    }
 }
 
 /// Represents a single agent instance.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Agent {
     pub id: String,
     pub session_id: String,
     pub agent_type: String, // e.g., "orchestrator", "worker"
     pub status: AgentStatus,
     pub pid: Option<u32>,
+    #[serde(default)]
+    pub process_started: Option<String>, // Start time of `pid`, to tell it from a reused pid after a restart
     pub result: Option<String>,
     pub progress: Option<u8>,
     pub last_thought: Option<String>,
     pub pending_interaction: Option<Interaction>, // Added interaction field
+    #[serde(default)]
+    pub usage: TokenUsage, // Tokens reported by the agent so far
+    #[serde(default = "Utc::now")]
+    pub started_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub last_activity_at: DateTime<Utc>, // Last report or output line, watched for idleness
+    #[serde(default)]
+    pub paused_secs: u64, // Time spent paused in earlier pauses
+    #[serde(default)]
+    pub paused_at: Option<DateTime<Utc>>, // Start of the current pause
+    #[serde(default)]
+    pub paused_from: Option<AgentStatus>, // Status the current pause interrupted
+    #[serde(default)]
+    pub worktree: Option<WorktreeInfo>, // Set when the agent works in its own git worktree
+    #[serde(default)]
+    pub rule_checks: Option<Vec<RuleCheck>>, // Project rule checks, once they have run
+    #[serde(default)]
+    pub work_checked: bool, // Post-run boundary and rule checks are done; its task completes only then
+    #[serde(default)]
+    pub parent_agent_id: Option<String>, // Agent that requested this one; None for session roots
+    #[serde(default)]
+    pub task_id: Option<String>, // Scheduled task the agent works on
+}
+
+/// An agent with the agents it requested, as returned by `AgentRegistry::agent_tree`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentNode {
+    #[serde(flatten)]
+    pub agent: Agent,
+    pub children: Vec<AgentNode>,
 }
 
 impl Agent {
@@ -50,11 +154,63 @@ impl Agent {
             agent_type,
             status: AgentStatus::Starting,
             pid: None,
+            process_started: None,
             result: None,
             progress: Some(0),
             last_thought: None,
             pending_interaction: None,
+            usage: TokenUsage::default(),
+            started_at: Utc::now(),
+            last_activity_at: Utc::now(),
+            paused_secs: 0,
+            paused_at: None,
+            paused_from: None,
+            worktree: None,
+            rule_checks: None,
+            work_checked: false,
+            parent_agent_id: None,
+            task_id: None,
+        }
+    }
+}
+
+impl Agent {
+    /// Sets the status, keeping track of the time spent paused, which does not
+    /// count against the agent's time limits.
+    pub fn set_status(&mut self, status: AgentStatus) {
+        let now = Utc::now();
+        if status == AgentStatus::Paused {
+            self.paused_at.get_or_insert(now);
+            if self.status != AgentStatus::Paused {
+                self.paused_from = Some(self.status.clone());
+            }
+        } else if let Some(paused_at) = self.paused_at.take() {
+            self.paused_from = None;
+            self.paused_secs += (now - paused_at).num_seconds().max(0) as u64;
+            // Nor is the idle limit counted from before the pause
+            self.last_activity_at = now;
         }
+        self.status = status;
+    }
+
+    /// The status a paused agent returns to when resumed: the one it was paused
+    /// in, unless that was waiting on a question that has since been answered.
+    pub fn status_after_pause(&self) -> AgentStatus {
+        let waiting = self
+            .pending_interaction
+            .as_ref()
+            .is_some_and(|interaction| interaction.status == InteractionStatus::Pending);
+        match &self.paused_from {
+            Some(AgentStatus::WaitingForInteraction) if !waiting => AgentStatus::Running,
+            Some(status) => status.clone(),
+            None => AgentStatus::Running,
+        }
+    }
+
+    /// Seconds since the agent started at `now`, not counting time spent paused.
+    pub fn running_secs(&self, now: DateTime<Utc>) -> i64 {
+        let pausing = self.paused_at.map_or(0, |paused_at| (now - paused_at).num_seconds().max(0));
+        (now - self.started_at).num_seconds() - self.paused_secs as i64 - pausing
     }
 }
 
@@ -62,15 +218,24 @@ impl Agent {
 #[derive(Clone, Default)]
 pub struct AgentRegistry {
     agents: Arc<Mutex<HashMap<String, Agent>>>,
+    events: AgentEventBus,
+    interaction_waiters: Arc<Mutex<HashMap<String, Arc<Notify>>>>, // interaction_id -> wakes `wait_for_interaction`
 }
 
 impl AgentRegistry {
     pub fn new() -> Self {
         Self {
             agents: Arc::new(Mutex::new(HashMap::new())),
+            interaction_waiters: Arc::new(Mutex::new(HashMap::new())),
+            events: AgentEventBus::new(),
         }
     }
 
+    /// Lifecycle events for the agents in this registry.
+    pub fn events(&self) -> &AgentEventBus {
+        &self.events
+    }
+
     /// Registers a new agent.
     pub fn register_agent(&self, agent: Agent) {
         let mut agents = self.agents.lock().unwrap();
@@ -87,7 +252,7 @@ impl AgentRegistry {
     pub fn update_status(&self, agent_id: &str, status: AgentStatus) -> Result<(), String> {
         let mut agents = self.agents.lock().unwrap();
         if let Some(agent) = agents.get_mut(agent_id) {
-            agent.status = status;
+            agent.set_status(status);
             Ok(())
         } else {
             Err(format!("Agent with ID {} not found", agent_id))
@@ -111,6 +276,14 @@ impl AgentRegistry {
         agents.remove(agent_id)
     }
 
+    /// Lists every agent, oldest first.
+    pub fn list_agents(&self) -> Vec<Agent> {
+        let agents = self.agents.lock().unwrap();
+        let mut list: Vec<Agent> = agents.values().cloned().collect();
+        list.sort_by_key(|agent| agent.started_at);
+        list
+    }
+
     /// Lists all agents for a specific session.
     pub fn list_agents_by_session(&self, session_id: &str) -> Vec<Agent> {
         let agents = self.agents.lock().unwrap();
@@ -130,7 +303,7 @@ impl AgentRegistry {
     ) -> Result<(), String> {
         let mut agents = self.agents.lock().unwrap();
         if let Some(agent) = agents.get_mut(agent_id) {
-            agent.status = status;
+            agent.set_status(status);
             agent.result = result;
             Ok(())
         } else {
@@ -138,6 +311,42 @@ impl AgentRegistry {
         }
     }
 
+    pub fn set_rule_checks(&self, agent_id: &str, checks: Vec<RuleCheck>) -> Result<(), String> {
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(agent) = agents.get_mut(agent_id) {
+            agent.rule_checks = Some(checks);
+            Ok(())
+        } else {
+            Err(format!("Agent with ID {} not found", agent_id))
+        }
+    }
+
+    /// Records that the hub finished checking the agent's work after it ran.
+    pub fn mark_work_checked(&self, agent_id: &str) -> Result<(), String> {
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(agent) = agents.get_mut(agent_id) {
+            agent.work_checked = true;
+            Ok(())
+        } else {
+            Err(format!("Agent with ID {} not found", agent_id))
+        }
+    }
+
+    /// Appends a section to the agent's result, starting one if it has none.
+    pub fn append_result(&self, agent_id: &str, section: &str) -> Result<(), String> {
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(agent) = agents.get_mut(agent_id) {
+            let result = agent.result.get_or_insert_with(String::new);
+            if !result.is_empty() {
+                result.push_str("\n\n");
+            }
+            result.push_str(section);
+            Ok(())
+        } else {
+            Err(format!("Agent with ID {} not found", agent_id))
+        }
+    }
+
     /// Updates the status, progress, and last thought of an existing agent.
     pub fn update_status_and_progress(
         &self,
@@ -148,9 +357,83 @@ impl AgentRegistry {
     ) -> Result<(), String> {
         let mut agents = self.agents.lock().unwrap();
         if let Some(agent) = agents.get_mut(agent_id) {
-            agent.status = status;
+            agent.set_status(status);
             agent.progress = Some(progress);
             agent.last_thought = thought;
+            agent.last_activity_at = Utc::now();
+            Ok(())
+        } else {
+            Err(format!("Agent with ID {} not found", agent_id))
+        }
+    }
+
+    /// Records that the agent just showed signs of life.
+    pub fn record_activity(&self, agent_id: &str) -> Result<(), String> {
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(agent) = agents.get_mut(agent_id) {
+            agent.last_activity_at = Utc::now();
+            Ok(())
+        } else {
+            Err(format!("Agent with ID {} not found", agent_id))
+        }
+    }
+
+    /// Records who requested the agent and for which task.
+    pub fn set_lineage(&self, agent_id: &str, parent_agent_id: Option<String>, task_id: Option<String>) -> Result<(), String> {
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(agent) = agents.get_mut(agent_id) {
+            agent.parent_agent_id = parent_agent_id;
+            agent.task_id = task_id;
+            Ok(())
+        } else {
+            Err(format!("Agent with ID {} not found", agent_id))
+        }
+    }
+
+    /// The session's agents as a forest, oldest first at each level. Agents
+    /// whose parent is unknown to the session become roots.
+    pub fn agent_tree(&self, session_id: &str) -> Vec<AgentNode> {
+        let mut agents = self.list_agents_by_session(session_id);
+        agents.sort_by_key(|agent| agent.started_at);
+        let ids: Vec<String> = agents.iter().map(|agent| agent.id.clone()).collect();
+        let mut children: HashMap<Option<String>, Vec<Agent>> = HashMap::new();
+        for agent in agents {
+            let parent = agent.parent_agent_id.clone().filter(|parent| ids.contains(parent) && *parent != agent.id);
+            children.entry(parent).or_default().push(agent);
+        }
+
+        fn build(agent: Agent, children: &mut HashMap<Option<String>, Vec<Agent>>) -> AgentNode {
+            let nodes = children
+                .remove(&Some(agent.id.clone()))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|child| build(child, children))
+                .collect();
+            AgentNode { agent, children: nodes }
+        }
+        children
+            .remove(&None)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|root| build(root, &mut children))
+            .collect()
+    }
+
+    pub fn set_worktree(&self, agent_id: &str, worktree: WorktreeInfo) -> Result<(), String> {
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(agent) = agents.get_mut(agent_id) {
+            agent.worktree = Some(worktree);
+            Ok(())
+        } else {
+            Err(format!("Agent with ID {} not found", agent_id))
+        }
+    }
+
+    /// Adds tokens reported by an agent to its running total.
+    pub fn add_usage(&self, agent_id: &str, usage: TokenUsage) -> Result<(), String> {
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(agent) = agents.get_mut(agent_id) {
+            agent.usage += usage;
             Ok(())
         } else {
             Err(format!("Agent with ID {} not found", agent_id))
@@ -158,42 +441,116 @@ impl AgentRegistry {
     }
 
     pub fn set_pending_interaction(&self, agent_id: &str, description: String) -> Result<String, String> {
+        self.raise_interaction(agent_id, Interaction::new(description))
+    }
+
+    /// Makes `interaction` the agent's pending question and returns its id.
+    pub fn raise_interaction(&self, agent_id: &str, interaction: Interaction) -> Result<String, String> {
         let mut agents = self.agents.lock().unwrap();
         if let Some(agent) = agents.get_mut(agent_id) {
-            let interaction_id = Uuid::new_v4().to_string();
-            agent.pending_interaction = Some(Interaction {
-                id: interaction_id.clone(),
-                description,
-                result: None,
-                status: InteractionStatus::Pending,
-            });
-            agent.status = AgentStatus::WaitingForInteraction;
+            let interaction_id = interaction.id.clone();
+            if let Some(replaced) = agent.pending_interaction.replace(interaction) {
+                self.wake_interaction_waiters(&replaced.id);
+            }
+            // Finished agents can carry questions raised on their behalf, e.g. merge conflicts
+            if agent.status.is_active() {
+                agent.set_status(AgentStatus::WaitingForInteraction);
+            }
+            agent.last_activity_at = Utc::now();
             Ok(interaction_id)
         } else {
             Err(format!("Agent with ID {} not found", agent_id))
         }
     }
 
+    /// Closes pending interactions whose deadline passed by `now` with their
+    /// default answer, and lets their agents run again. Returns the agents
+    /// whose interaction expired.
+    pub fn expire_interactions(&self, now: DateTime<Utc>) -> Vec<Agent> {
+        let mut agents = self.agents.lock().unwrap();
+        let mut expired = Vec::new();
+        for agent in agents.values_mut() {
+            let Some(interaction) = &mut agent.pending_interaction else {
+                continue;
+            };
+            if interaction.status != InteractionStatus::Pending || interaction.deadline.is_none_or(|deadline| deadline > now) {
+                continue;
+            }
+            interaction.status = InteractionStatus::Expired;
+            interaction.result = interaction.default_answer.clone();
+            if agent.status == AgentStatus::WaitingForInteraction {
+                agent.status = AgentStatus::Running;
+            }
+            agent.last_activity_at = now;
+            self.wake_interaction_waiters(&interaction.id);
+            expired.push(agent.clone());
+        }
+        expired
+    }
+
     pub fn resolve_interaction(&self, interaction_id: &str, answer: String) -> Result<(), String> {
         let mut agents = self.agents.lock().unwrap();
         // Find the agent with this pending interaction
         let agent_id = agents.values()
-            .find(|a| a.pending_interaction.as_ref().map_or(false, |i| i.id == interaction_id))
+            .find(|a| a.pending_interaction.as_ref().is_some_and(|i| i.id == interaction_id))
             .map(|a| a.id.clone());
 
         if let Some(id) = agent_id {
             let agent = agents.get_mut(&id).unwrap();
             if let Some(interaction) = &mut agent.pending_interaction {
+                if interaction.status == InteractionStatus::Expired {
+                    return Err(format!("Interaction with ID {} expired", interaction_id));
+                }
                 interaction.result = Some(answer);
                 interaction.status = InteractionStatus::Resolved;
+                self.wake_interaction_waiters(interaction_id);
+            }
+            if agent.status == AgentStatus::WaitingForInteraction {
+                agent.status = AgentStatus::Running; // Resume running
             }
-            agent.status = AgentStatus::Running; // Resume running
+            agent.last_activity_at = Utc::now(); // Idle time starts again after the answer
             Ok(())
         } else {
             Err(format!("Interaction with ID {} not found", interaction_id))
         }
     }
 
+    /// Waits until the interaction is answered, expires or is replaced, or
+    /// until `timeout` (or the interaction's own deadline) passes, and returns
+    /// its state then. `None` if there is no such interaction.
+    pub async fn wait_for_interaction(&self, interaction_id: &str, timeout: Duration) -> Option<Interaction> {
+        let notify = self
+            .interaction_waiters
+            .lock()
+            .unwrap()
+            .entry(interaction_id.to_string())
+            .or_default()
+            .clone();
+        let notified = notify.notified();
+        tokio::pin!(notified);
+        // Registered before the check, so an answer arriving in between is not missed
+        notified.as_mut().enable();
+
+        let interaction = self.get_interaction_status(interaction_id);
+        let Some(interaction) = interaction.filter(|i| i.status == InteractionStatus::Pending) else {
+            // Nothing will wake this entry, so do not leave it behind
+            self.wake_interaction_waiters(interaction_id);
+            return self.get_interaction_status(interaction_id);
+        };
+        let until_deadline = interaction
+            .deadline
+            .map(|deadline| (deadline - Utc::now()).to_std().unwrap_or_default())
+            .unwrap_or(timeout);
+        let _ = tokio::time::timeout(timeout.min(until_deadline), notified).await;
+        self.get_interaction_status(interaction_id)
+    }
+
+    fn wake_interaction_waiters(&self, interaction_id: &str) {
+        if let Some(notify) = self.interaction_waiters.lock().unwrap().remove(interaction_id) {
+            notify.notify_waiters();
+        }
+    }
+
     pub fn get_interaction_status(&self, interaction_id: &str) -> Option<Interaction> {
         let agents = self.agents.lock().unwrap();
         agents.values()
@@ -280,4 +637,95 @@ mod tests {
         let session_a_agents = registry.list_agents_by_session("session-A");
         assert_eq!(session_a_agents.len(), 2);
     }
+
+    #[test]
+    fn test_add_usage_accumulates() {
+        let registry = AgentRegistry::new();
+        let agent = Agent::new("session-123".to_string(), "worker".to_string());
+        let agent_id = agent.id.clone();
+
+        registry.register_agent(agent);
+        registry.add_usage(&agent_id, TokenUsage::new(10, 5)).unwrap();
+        registry.add_usage(&agent_id, TokenUsage::new(1, 2)).unwrap();
+
+        assert_eq!(registry.get_agent(&agent_id).unwrap().usage, TokenUsage::new(11, 7));
+    }
+
+    #[test]
+    fn test_agent_tree_nests_children_under_parents() {
+        let registry = AgentRegistry::new();
+        let root = Agent::new("s1".to_string(), "orchestrator".to_string());
+        let root_id = root.id.clone();
+        let worker = Agent::new("s1".to_string(), "worker".to_string());
+        let worker_id = worker.id.clone();
+        let sub = Agent::new("s1".to_string(), "worker".to_string());
+        let sub_id = sub.id.clone();
+        let orphan = Agent::new("s1".to_string(), "worker".to_string());
+        let orphan_id = orphan.id.clone();
+        for agent in [root, worker, sub, orphan] {
+            registry.register_agent(agent);
+        }
+        registry.register_agent(Agent::new("s2".to_string(), "orchestrator".to_string()));
+        registry.set_lineage(&worker_id, Some(root_id.clone()), Some("t1".to_string())).unwrap();
+        registry.set_lineage(&sub_id, Some(worker_id.clone()), Some("sub-1".to_string())).unwrap();
+        registry.set_lineage(&orphan_id, Some("gone".to_string()), None).unwrap();
+
+        let tree = registry.agent_tree("s1");
+        let roots: Vec<&str> = tree.iter().map(|node| node.agent.id.as_str()).collect();
+        assert_eq!(roots.len(), 2);
+        assert!(roots.contains(&root_id.as_str()) && roots.contains(&orphan_id.as_str()));
+        let root = tree.iter().find(|node| node.agent.id == root_id).unwrap();
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].agent.id, worker_id);
+        assert_eq!(root.children[0].children[0].agent.id, sub_id);
+    }
+
+    #[test]
+    fn test_expired_interaction_falls_back_to_default() {
+        let registry = AgentRegistry::new();
+        let mut agent = Agent::new("s1".to_string(), "worker".to_string());
+        agent.status = AgentStatus::Running;
+        let agent_id = agent.id.clone();
+        registry.register_agent(agent);
+
+        let now = Utc::now();
+        let interaction = Interaction::new("Deploy?".to_string())
+            .with_kind(InteractionKind::Confirm)
+            .with_default_answer(Some("no".to_string()))
+            .with_deadline(Some(now + chrono::Duration::seconds(30)));
+        let interaction_id = registry.raise_interaction(&agent_id, interaction).unwrap();
+        assert!(registry.expire_interactions(now).is_empty());
+
+        let expired = registry.expire_interactions(now + chrono::Duration::seconds(31));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].status, AgentStatus::Running);
+        let interaction = registry.get_interaction_status(&interaction_id).unwrap();
+        assert_eq!(interaction.status, InteractionStatus::Expired);
+        assert_eq!(interaction.result.as_deref(), Some("no"));
+        assert!(registry.resolve_interaction(&interaction_id, "yes".to_string()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_interaction_returns_on_answer_or_timeout() {
+        let registry = AgentRegistry::new();
+        let agent = Agent::new("s1".to_string(), "worker".to_string());
+        let agent_id = agent.id.clone();
+        registry.register_agent(agent);
+        let interaction_id = registry.set_pending_interaction(&agent_id, "Which port?".to_string()).unwrap();
+
+        let pending = registry.wait_for_interaction(&interaction_id, Duration::from_millis(20)).await.unwrap();
+        assert_eq!(pending.status, InteractionStatus::Pending);
+
+        let waiter = {
+            let registry = registry.clone();
+            let interaction_id = interaction_id.clone();
+            tokio::spawn(async move { registry.wait_for_interaction(&interaction_id, Duration::from_secs(30)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        registry.resolve_interaction(&interaction_id, "8080".to_string()).unwrap();
+        let answered = tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap().unwrap();
+        assert_eq!(answered.status, InteractionStatus::Resolved);
+        assert_eq!(answered.result.as_deref(), Some("8080"));
+        assert!(registry.wait_for_interaction("unknown", Duration::from_secs(30)).await.is_none());
+    }
 }
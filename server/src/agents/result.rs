@@ -0,0 +1,242 @@
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::tasks::{Task, TaskGraph, TaskGraphError};
+
+pub const AGENT_RESULT_VERSION: u32 = 1;
+pub const RESULT_JSON_FILE: &str = "RESULT.json";
+/// Written next to an invalid RESULT.json so the agent can fix it and retry.
+pub const RESULT_ERRORS_FILE: &str = "RESULT_ERRORS.md";
+
+/// Appended to agent instructions to describe the RESULT.json contract.
+pub const RESULT_JSON_GUIDE: &str = r#"
+## Reporting your result
+
+When you are done, write `RESULT.json` in your agent directory (your working directory unless you
+were told otherwise), then call `vibe-complete`:
+
+```json
+{
+  "version": 1,
+  "status": "success",
+  "summary": "What you did, in a few sentences",
+  "files_changed": ["src/main.rs"],
+  "follow_up_tasks": [
+    { "id": "docs-1", "description": "Document the new flag", "agent_type": "worker" }
+  ],
+  "errors": []
+}
+```
+
+`status` is one of `success`, `partial` or `failed`; a `failed` result must list what went wrong in `errors`.
+`follow_up_tasks` use the task graph format and are scheduled in this session.
+If the file is rejected, the problems are written to `RESULT_ERRORS.md`; fix them and write `RESULT.json` again.
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentResultStatus {
+    Success,
+    Partial,
+    Failed,
+}
+
+/// The structured outcome an agent writes to `RESULT.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentResult {
+    pub version: u32,
+    pub status: AgentResultStatus,
+    pub summary: String,
+    #[serde(default)]
+    pub files_changed: Vec<String>,
+    #[serde(default)]
+    pub follow_up_tasks: Vec<Task>,
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum AgentResultError {
+    #[error("RESULT.json is not valid JSON: {0}")]
+    Syntax(serde_json::Error),
+    #[error("RESULT.json must have a numeric `version` field (current version is {AGENT_RESULT_VERSION})")]
+    MissingVersion,
+    #[error("RESULT.json version {0} is not supported (current version is {AGENT_RESULT_VERSION})")]
+    UnsupportedVersion(u64),
+    #[error("RESULT.json does not match the schema: {0}")]
+    Schema(serde_json::Error),
+    #[error("`summary` must not be empty")]
+    EmptySummary,
+    #[error("a result with status `failed` must list at least one entry in `errors`")]
+    MissingErrors,
+    #[error("`follow_up_tasks` is not a valid task graph: {0}")]
+    FollowUpTasks(#[from] TaskGraphError),
+}
+
+impl AgentResult {
+    /// Parses and validates the contents of a `RESULT.json`.
+    pub fn parse(raw: &str) -> Result<Self, AgentResultError> {
+        let value: Value = serde_json::from_str(raw).map_err(AgentResultError::Syntax)?;
+        // Check the version before the shape, so a newer format is reported as such
+        match value.get("version").and_then(Value::as_u64) {
+            None => return Err(AgentResultError::MissingVersion),
+            Some(version) if version != AGENT_RESULT_VERSION as u64 => {
+                return Err(AgentResultError::UnsupportedVersion(version))
+            }
+            Some(_) => {}
+        }
+        let result: AgentResult = serde_json::from_value(value).map_err(AgentResultError::Schema)?;
+        result.validate()?;
+        Ok(result)
+    }
+
+    /// Checks the result on its own. Follow-up tasks may depend on tasks already
+    /// scheduled in the session, so their dependencies are checked when they are
+    /// dispatched rather than here.
+    pub fn validate(&self) -> Result<(), AgentResultError> {
+        if self.summary.trim().is_empty() {
+            return Err(AgentResultError::EmptySummary);
+        }
+        if self.status == AgentResultStatus::Failed && self.errors.is_empty() {
+            return Err(AgentResultError::MissingErrors);
+        }
+        if let Some(graph) = self.follow_up_graph() {
+            graph.validate_tasks()?;
+        }
+        Ok(())
+    }
+
+    /// Wraps a legacy free-form `RESULT.md`, picking up a task graph embedded
+    /// in it (either the whole file or a ```json block) as follow-up tasks.
+    pub fn from_markdown(content: &str) -> Self {
+        let follow_up_tasks = embedded_task_graph(content)
+            .map(|graph| graph.tasks)
+            .unwrap_or_default();
+        Self {
+            version: AGENT_RESULT_VERSION,
+            status: AgentResultStatus::Success,
+            summary: content.to_string(),
+            files_changed: Vec::new(),
+            follow_up_tasks,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn follow_up_graph(&self) -> Option<TaskGraph> {
+        if self.follow_up_tasks.is_empty() {
+            return None;
+        }
+        Some(TaskGraph {
+            tasks: self.follow_up_tasks.clone(),
+        })
+    }
+}
+
+fn embedded_task_graph(content: &str) -> Option<TaskGraph> {
+    if !content.contains("\"tasks\"") {
+        return None;
+    }
+    let json = match content.find("```json") {
+        Some(start) => {
+            let block = &content[start + "```json".len()..];
+            &block[..block.find("```")?]
+        }
+        None => content,
+    };
+    serde_json::from_str(json.trim()).ok()
+}
+
+/// Records why a RESULT.json was rejected in the agent's directory.
+pub fn write_result_errors(agent_dir: &Path, error: &AgentResultError) -> io::Result<()> {
+    let report = format!(
+        "# RESULT.json was rejected\n\n{}\n\nFix the file and write it again.\n{}",
+        error, RESULT_JSON_GUIDE
+    );
+    std::fs::write(agent_dir.join(RESULT_ERRORS_FILE), report)
+}
+
+/// Removes a stale error report once a valid result has been accepted.
+pub fn clear_result_errors(agent_dir: &Path) -> io::Result<()> {
+    match std::fs::remove_file(agent_dir.join(RESULT_ERRORS_FILE)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parses_valid_result() {
+        let result = AgentResult::parse(
+            r#"{
+                "version": 1,
+                "status": "partial",
+                "summary": "Added the flag",
+                "files_changed": ["src/main.rs"],
+                "follow_up_tasks": [{ "id": "t1", "description": "Write docs", "agent_type": null }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(result.status, AgentResultStatus::Partial);
+        assert_eq!(result.follow_up_graph().unwrap().tasks[0].id, "t1");
+        assert!(result.errors.is_empty());
+
+        // Dependencies on tasks outside the result are left to the schedule
+        let result = AgentResult::parse(
+            r#"{"version": 1, "status": "success", "summary": "x",
+                "follow_up_tasks": [{"id": "a", "description": "d", "agent_type": null, "depends_on": ["init-1"]}]}"#,
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_rejections_explain_the_problem() {
+        let cases = [
+            (r#"{"version": 1, "status": "#, "not valid JSON"),
+            (r#"{"status": "success", "summary": "x"}"#, "`version`"),
+            (r#"{"version": 2, "status": "success", "summary": "x"}"#, "version 2"),
+            (r#"{"version": 1, "status": "done", "summary": "x"}"#, "unknown variant `done`"),
+            (r#"{"version": 1, "status": "success", "summary": "x", "extra": 1}"#, "unknown field `extra`"),
+            (r#"{"version": 1, "status": "success", "summary": " "}"#, "`summary`"),
+            (r#"{"version": 1, "status": "failed", "summary": "x"}"#, "`errors`"),
+            (
+                r#"{"version": 1, "status": "success", "summary": "x",
+                    "follow_up_tasks": [{"id": "a", "description": "d", "agent_type": null, "depends_on": ["a"]}]}"#,
+                "dependency cycle",
+            ),
+        ];
+        for (raw, expected) in cases {
+            let message = AgentResult::parse(raw).unwrap_err().to_string();
+            assert!(message.contains(expected), "{:?} should mention {:?}", message, expected);
+        }
+    }
+
+    #[test]
+    fn test_markdown_fallback_extracts_task_block() {
+        let result = AgentResult::from_markdown(
+            "Plan ready.\n```json\n{\"tasks\": [{\"id\": \"a\", \"description\": \"d\", \"agent_type\": null}]}\n```\n",
+        );
+        assert_eq!(result.status, AgentResultStatus::Success);
+        assert_eq!(result.follow_up_tasks.len(), 1);
+        assert!(AgentResult::from_markdown("No plan").follow_up_tasks.is_empty());
+    }
+
+    #[test]
+    fn test_error_report_is_written_and_cleared() {
+        let dir = tempdir().unwrap();
+        write_result_errors(dir.path(), &AgentResultError::EmptySummary).unwrap();
+        let report = std::fs::read_to_string(dir.path().join(RESULT_ERRORS_FILE)).unwrap();
+        assert!(report.contains("`summary` must not be empty"));
+
+        clear_result_errors(dir.path()).unwrap();
+        clear_result_errors(dir.path()).unwrap();
+        assert!(!dir.path().join(RESULT_ERRORS_FILE).exists());
+    }
+}
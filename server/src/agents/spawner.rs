@@ -1,23 +1,211 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 use tokio::process::Command;
 use std::process::Stdio; // Use tokio's Command
+use crate::agents::events::{AgentEvent, AgentOutput, OutputStream};
+use crate::agents::output::{log_entry, OutputLog, DEBUG_LOG_FILE};
 use crate::agents::registry::{Agent, AgentRegistry, AgentStatus};
-use tokio::io::{AsyncWriteExt, BufReader, AsyncBufReadExt}; // For async file I/O
-use tracing::{info, error};
+use crate::utils::process::{process_group_alive, process_start_time, signal_process_group};
+use thiserror::Error;
+use tokio::io::{BufReader, AsyncBufReadExt}; // For async file I/O
+use tracing::{info, error, warn};
+
+/// How long a killed agent gets to exit after SIGTERM before it is sent SIGKILL.
+pub const DEFAULT_KILL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How long output is still read after the agent exited. Processes it started
+/// may hold its pipes open for much longer.
+const EXIT_DRAIN_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// How often the process group of an agent re-attached after a restart is checked.
+const REATTACHED_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Error)]
+pub enum AgentControlError {
+    #[error("agent {0} not found")]
+    NotFound(String),
+    #[error("agent {agent_id} is {status:?} and cannot be {action}")]
+    InvalidState {
+        agent_id: String,
+        status: AgentStatus,
+        action: &'static str,
+    },
+    #[error("agent {0} has no process id")]
+    NoProcess(String),
+    #[error("failed to signal agent process group: {0}")]
+    Signal(#[from] std::io::Error),
+}
 
 #[derive(Clone)]
 pub struct AgentSpawner {
     registry: AgentRegistry,
     base_dir: PathBuf,
+    kill_grace_period: Duration,
 }
 
 impl AgentSpawner {
     pub fn new(registry: AgentRegistry, base_dir: PathBuf) -> Self {
-        Self { registry, base_dir }
+        Self { registry, base_dir, kill_grace_period: DEFAULT_KILL_GRACE_PERIOD }
+    }
+
+    pub fn with_kill_grace_period(mut self, grace_period: Duration) -> Self {
+        self.kill_grace_period = grace_period;
+        self
+    }
+
+    /// Sends SIGTERM to the agent's process group and SIGKILL to whatever is
+    /// left of it once the grace period has passed.
+    pub fn kill_agent(&self, agent_id: &str) -> Result<Agent, AgentControlError> {
+        self.stop_agent(agent_id, AgentStatus::Terminated)
+    }
+
+    /// Kills the agent like `kill_agent`, but records the attempt as failed
+    /// with `reason` rather than terminated, so its task may be retried.
+    pub fn fail_agent(&self, agent_id: &str, reason: String) -> Result<Agent, AgentControlError> {
+        self.stop_agent(agent_id, AgentStatus::Failed(reason))
+    }
+
+    fn stop_agent(&self, agent_id: &str, status: AgentStatus) -> Result<Agent, AgentControlError> {
+        let (agent, pgid) = self.controllable_agent(agent_id, "killed", |status| status.is_active())?;
+
+        // The final status goes first, so the exit it causes is not taken for the agent's own
+        self.set_status(&agent, status)?;
+        signal_process_group(pgid, "TERM")?;
+        if agent.status == AgentStatus::Paused {
+            // A stopped process only acts on SIGTERM once it is continued
+            signal_process_group(pgid, "CONT")?;
+        }
+
+        let grace_period = self.kill_grace_period;
+        let agent_id_for_log = agent_id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(grace_period).await;
+            if process_group_alive(pgid) {
+                warn!("Agent {} outlived its grace period; sending SIGKILL", agent_id_for_log);
+                if let Err(e) = signal_process_group(pgid, "KILL") {
+                    error!("Failed to SIGKILL agent {}: {}", agent_id_for_log, e);
+                }
+            }
+        });
+
+        info!("Terminated agent {} (process group {})", agent_id, pgid);
+        self.registry.get_agent(agent_id).ok_or_else(|| AgentControlError::NotFound(agent_id.to_string()))
+    }
+
+    /// Stops the agent's process group with SIGSTOP.
+    pub fn pause_agent(&self, agent_id: &str) -> Result<Agent, AgentControlError> {
+        let (agent, pgid) = self.controllable_agent(agent_id, "paused", |status| {
+            matches!(status, AgentStatus::Running | AgentStatus::WaitingForInteraction)
+        })?;
+        signal_process_group(pgid, "STOP")?;
+        self.set_status(&agent, AgentStatus::Paused)?;
+        info!("Paused agent {} (process group {})", agent_id, pgid);
+        self.registry.get_agent(agent_id).ok_or_else(|| AgentControlError::NotFound(agent_id.to_string()))
+    }
+
+    /// Continues a paused agent's process group with SIGCONT, in the status it
+    /// was paused in.
+    pub fn resume_agent(&self, agent_id: &str) -> Result<Agent, AgentControlError> {
+        let (agent, pgid) = self.controllable_agent(agent_id, "resumed", |status| *status == AgentStatus::Paused)?;
+        signal_process_group(pgid, "CONT")?;
+        self.set_status(&agent, agent.status_after_pause())?;
+        info!("Resumed agent {} (process group {})", agent_id, pgid);
+        self.registry.get_agent(agent_id).ok_or_else(|| AgentControlError::NotFound(agent_id.to_string()))
+    }
+
+    /// Looks up an agent whose status allows `action`, returning it with its process group id.
+    fn controllable_agent(
+        &self,
+        agent_id: &str,
+        action: &'static str,
+        allowed: impl Fn(&AgentStatus) -> bool,
+    ) -> Result<(Agent, u32), AgentControlError> {
+        let agent = self
+            .registry
+            .get_agent(agent_id)
+            .ok_or_else(|| AgentControlError::NotFound(agent_id.to_string()))?;
+        if !allowed(&agent.status) {
+            return Err(AgentControlError::InvalidState {
+                agent_id: agent_id.to_string(),
+                status: agent.status,
+                action,
+            });
+        }
+        // Agents are spawned as group leaders, so the pid doubles as the process group id
+        let pgid = agent.pid.ok_or_else(|| AgentControlError::NoProcess(agent_id.to_string()))?;
+        Ok((agent, pgid))
+    }
+
+    fn set_status(&self, agent: &Agent, status: AgentStatus) -> Result<(), AgentControlError> {
+        self.registry
+            .update_status(&agent.id, status.clone())
+            .map_err(|_| AgentControlError::NotFound(agent.id.clone()))?;
+        self.registry.events().publish(AgentEvent::StatusChanged {
+            agent_id: agent.id.clone(),
+            session_id: agent.session_id.clone(),
+            status,
+        });
+        Ok(())
+    }
+
+    /// Watches an agent whose process outlived a server restart. Its output is
+    /// no longer captured, so a live process group counts as activity. Once the
+    /// group is gone the agent gets a final status and an `Exited` event.
+    pub fn reattach(&self, agent_id: &str) {
+        let Some(pgid) = self.registry.get_agent(agent_id).and_then(|agent| agent.pid) else {
+            return;
+        };
+        let registry = self.registry.clone();
+        let agent_id = agent_id.to_string();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(REATTACHED_POLL_INTERVAL).await;
+                let alive = tokio::task::spawn_blocking(move || process_group_alive(pgid))
+                    .await
+                    .unwrap_or(false);
+                if !alive {
+                    break;
+                }
+                let _ = registry.record_activity(&agent_id);
+            }
+
+            let Some(agent) = registry.get_agent(&agent_id) else {
+                return;
+            };
+            // The exit code is lost with the original parent, so a result is the only sign of success
+            let status = if !agent.status.is_active() {
+                agent.status
+            } else if agent.result.is_some() {
+                AgentStatus::Completed
+            } else {
+                AgentStatus::Failed("process exited after a server restart without a result".to_string())
+            };
+            if let Err(e) = registry.update_status(&agent_id, status.clone()) {
+                error!("Failed to update re-attached agent {} after exit: {}", agent_id, e);
+            }
+            info!("Re-attached agent {} exited", agent_id);
+            registry.events().publish(AgentEvent::Exited {
+                agent_id,
+                session_id: agent.session_id,
+                code: None,
+                status,
+            });
+        });
+    }
+
+    /// Where the agent's output is logged.
+    pub fn debug_log_path(&self, agent: &Agent) -> PathBuf {
+        self.base_dir
+            .join(".vibe")
+            .join("agents")
+            .join(&agent.session_id)
+            .join(&agent.id)
+            .join(DEBUG_LOG_FILE)
     }
 
     /// Spawns a new agent for a given session.
+    #[allow(clippy::too_many_arguments)]
     pub async fn spawn_agent( // Make this async
         &self, 
         session_id: String, 
@@ -27,6 +215,7 @@ impl AgentSpawner {
         args: Vec<String>,
         env_vars: HashMap<String, String>,
         agent_id_override: Option<String>,
+        working_dir: Option<PathBuf>, // Defaults to the agent's directory
     ) -> Result<String, String> {
         let mut agent = Agent::new(session_id.clone(), agent_type.clone());
         if let Some(id) = agent_id_override {
@@ -45,103 +234,144 @@ impl AgentSpawner {
         }
 
         // 4. Spawn Process and capture stdout/stderr
-        let debug_log_path = agent_dir.join("debug_log.txt");
-        let _debug_log_file = tokio::fs::File::create(&debug_log_path) // _ to suppress unused warning
+        let debug_log_path = agent_dir.join(DEBUG_LOG_FILE);
+        let mut log = OutputLog::create(&debug_log_path, &agent_id)
             .await
             .map_err(|e| format!("Failed to create debug_log.txt at {:?}: {}", debug_log_path, e))?;
 
         let child_result = Command::new(&command_str) // Use tokio::process::Command
             .args(&args)
             .envs(&env_vars)
-            .current_dir(&agent_dir)
+            .current_dir(working_dir.as_deref().unwrap_or(&agent_dir))
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .process_group(0) // Own group, so signals reach the agent's children too
             .spawn();
 
         match child_result {
             Ok(mut child) => {
                 agent.pid = child.id(); // Assign Option<u32> directly
                 agent.status = AgentStatus::Running;
+                if let Some(pid) = agent.pid {
+                    agent.process_started = tokio::task::spawn_blocking(move || process_start_time(pid))
+                        .await
+                        .ok()
+                        .flatten();
+                }
+                let pid = agent.pid;
                 self.registry.register_agent(agent);
+                self.registry.events().publish(AgentEvent::Spawned {
+                    agent_id: agent_id.clone(),
+                    session_id: session_id.clone(),
+                    pid,
+                });
 
                 let stdout = child.stdout.take().expect("Failed to capture stdout");
                 let stderr = child.stderr.take().expect("Failed to capture stderr");
                 
                 let agent_id_for_log = agent_id.clone();
                 let registry_clone = self.registry.clone(); // Clone registry for the spawned task
+                let session_id_for_log = session_id.clone();
 
                 tokio::spawn(async move {
                     let mut reader_stdout = BufReader::new(stdout);
                     let mut reader_stderr = BufReader::new(stderr);
-                    let mut stdout_line = String::new(); // Separate buffer for stdout
-                    let mut stderr_line = String::new(); // Separate buffer for stderr
+                    // Raw bytes, so output that is not valid UTF-8 is decoded lossily
+                    // instead of ending the reader
+                    let mut stdout_line = Vec::new(); // Separate buffer for stdout
+                    let mut stderr_line = Vec::new(); // Separate buffer for stderr
 
-                    let mut log_file = tokio::fs::OpenOptions::new()
-                        .append(true)
-                        .create(false) // file should already exist
-                        .open(&debug_log_path)
-                        .await
-                        .expect("Failed to open debug log file for appending");
+                    let mut stdout_done = false;
+                    let mut stderr_done = false;
+                    let mut exit_status = None;
+                    let mut drain_until = tokio::time::Instant::now();
 
-                    loop {
+                    // Wait for the exit alongside the readers, then keep draining briefly so
+                    // trailing output is not lost
+                    while !(stdout_done && stderr_done && exit_status.is_some()) {
                         tokio::select! {
-                            result_stdout = reader_stdout.read_line(&mut stdout_line) => {
+                            status = child.wait(), if exit_status.is_none() => {
+                                exit_status = Some(status);
+                                drain_until = tokio::time::Instant::now() + EXIT_DRAIN_GRACE_PERIOD;
+                            }
+                            _ = tokio::time::sleep_until(drain_until), if exit_status.is_some() => {
+                                warn!("Agent {} exited but its output is still open; no longer reading it", agent_id_for_log);
+                                break;
+                            }
+                            result_stdout = reader_stdout.read_until(b'\n', &mut stdout_line), if !stdout_done => {
                                 match result_stdout {
-                                    Ok(0) => break, // EOF
+                                    Ok(0) => stdout_done = true, // EOF
                                     Ok(_) => {
-                                        let log_entry = format!("[{}] STDOUT: {}", agent_id_for_log, stdout_line.trim_end());
-                                        log_file.write_all(log_entry.as_bytes()).await.expect("Failed to write to log");
-                                        log_file.write_all(b"\n").await.expect("Failed to write newline to log");
-                                        info!("{}", log_entry); // Also log to server's info stream
+                                        let line = String::from_utf8_lossy(&stdout_line);
+                                        let line = line.trim_end();
+                                        info!("{}", log_entry(&agent_id_for_log, OutputStream::Stdout, line)); // Also log to server's info stream
+                                        record_output(&mut log, &registry_clone, &agent_id_for_log, &session_id_for_log, OutputStream::Stdout, line).await;
                                         stdout_line.clear();
                                     },
                                     Err(e) => {
                                         error!("Error reading stdout for agent {}: {}", agent_id_for_log, e);
-                                        break;
+                                        stdout_done = true;
                                     }
                                 }
                             }
-                            result_stderr = reader_stderr.read_line(&mut stderr_line) => { // Use stderr_line
+                            result_stderr = reader_stderr.read_until(b'\n', &mut stderr_line), if !stderr_done => { // Use stderr_line
                                 match result_stderr {
-                                    Ok(0) => break, // EOF
+                                    Ok(0) => stderr_done = true, // EOF
                                     Ok(_) => {
-                                        let log_entry = format!("[{}] STDERR: {}", agent_id_for_log, stderr_line.trim_end());
-                                        log_file.write_all(log_entry.as_bytes()).await.expect("Failed to write to log");
-                                        log_file.write_all(b"\n").await.expect("Failed to write newline to log");
-                                        error!("{}", log_entry); // Also log to server's error stream
+                                        let line = String::from_utf8_lossy(&stderr_line);
+                                        let line = line.trim_end();
+                                        error!("{}", log_entry(&agent_id_for_log, OutputStream::Stderr, line)); // Also log to server's error stream
+                                        record_output(&mut log, &registry_clone, &agent_id_for_log, &session_id_for_log, OutputStream::Stderr, line).await;
                                         stderr_line.clear();
                                     },
                                     Err(e) => {
                                         error!("Error reading stderr for agent {}: {}", agent_id_for_log, e);
-                                        break;
+                                        stderr_done = true;
                                     }
                                 }
                             }
-                            // Also await child process exit to update its status
-                            exit_status = child.wait() => {
-                                match exit_status {
-                                    Ok(status) => {
-                                        let final_status = if status.success() {
-                                            AgentStatus::Completed
-                                        } else {
-                                            AgentStatus::Failed(format!("Exited with status: {:?}", status))
-                                        };
-                                        if let Err(e) = registry_clone.update_status(&agent_id_for_log, final_status) {
-                                            error!("Failed to update agent {} status after exit: {}", agent_id_for_log, e);
-                                        }
-                                        info!("Agent {} process exited with status: {:?}.", agent_id_for_log, status);
-                                    },
-                                    Err(e) => {
-                                        error!("Error waiting for agent {} process: {}", agent_id_for_log, e);
-                                        if let Err(e) = registry_clone.update_status(&agent_id_for_log, AgentStatus::Failed(format!("Process wait error: {}", e))) {
-                                            error!("Failed to update agent {} status after wait error: {}", agent_id_for_log, e);
-                                        }
-                                    }
-                                }
-                                break;
+                        }
+                    }
+
+                    let exit_status = exit_status.expect("the output loop only ends after the exit");
+                    match &exit_status {
+                        // Killed or timed-out agents already carry their final status
+                        Ok(status) if registry_clone.get_agent(&agent_id_for_log).is_some_and(|a| !a.status.is_active()) => {
+                            info!("Stopped agent {} exited with status: {:?}.", agent_id_for_log, status);
+                        },
+                        Ok(status) => {
+                            let final_status = if status.success() {
+                                AgentStatus::Completed
+                            } else {
+                                AgentStatus::Failed(format!("Exited with status: {:?}", status))
+                            };
+                            if let Err(e) = registry_clone.update_status(&agent_id_for_log, final_status) {
+                                error!("Failed to update agent {} status after exit: {}", agent_id_for_log, e);
+                            }
+                            info!("Agent {} process exited with status: {:?}.", agent_id_for_log, status);
+                        },
+                        Err(e) => {
+                            error!("Error waiting for agent {} process: {}", agent_id_for_log, e);
+                            let reason = format!("Process wait error: {}", e);
+                            if let Err(e) = registry_clone.update_status(&agent_id_for_log, AgentStatus::Failed(reason.clone())) {
+                                error!("Failed to update agent {} status after wait error: {}", agent_id_for_log, e);
                             }
+                            registry_clone.events().publish(AgentEvent::Failed {
+                                agent_id: agent_id_for_log,
+                                session_id: session_id_for_log,
+                                reason,
+                            });
+                            return;
                         }
                     }
+                    if let (Ok(status), Some(agent)) = (exit_status, registry_clone.get_agent(&agent_id_for_log)) {
+                        registry_clone.events().publish(AgentEvent::Exited {
+                            agent_id: agent_id_for_log,
+                            session_id: session_id_for_log,
+                            code: status.code(),
+                            status: agent.status,
+                        });
+                    }
                 });
                 
                 Ok(agent_id)
@@ -149,12 +379,39 @@ impl AgentSpawner {
             Err(e) => {
                 agent.status = AgentStatus::Failed(e.to_string());
                 self.registry.register_agent(agent);
+                self.registry.events().publish(AgentEvent::Failed {
+                    agent_id,
+                    session_id,
+                    reason: e.to_string(),
+                });
                 Err(format!("Failed to spawn command '{}': {}", command_str, e))
             }
         }
     }
 }
 
+/// Writes one line of an agent's output to its debug log and publishes it.
+async fn record_output(
+    log: &mut OutputLog,
+    registry: &AgentRegistry,
+    agent_id: &str,
+    session_id: &str,
+    stream: OutputStream,
+    line: &str,
+) {
+    let _ = registry.record_activity(agent_id);
+    match log.append(stream, line).await {
+        Ok(seq) => registry.events().publish_output(AgentOutput {
+            agent_id: agent_id.to_string(),
+            session_id: session_id.to_string(),
+            stream,
+            seq,
+            line: line.to_string(),
+        }),
+        Err(e) => error!("Failed to write output of agent {} to its debug log: {}", agent_id, e),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,6 +434,8 @@ mod tests {
                 "echo".to_string(), // Use echo for test command
                 vec!["Hello from agent".to_string()],
                 HashMap::new(),
+                None,
+                None,
             ).await; // Await the async call
 
             assert!(agent_id_result.is_ok());
@@ -194,7 +453,7 @@ mod tests {
 
             // Verify debug_log.txt (give some time for async task)
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-            let debug_log_path = agent_dir.join("debug_log.txt");
+            let debug_log_path = agent_dir.join(DEBUG_LOG_FILE);
             assert!(debug_log_path.exists());
             let debug_content = tokio::fs::read_to_string(debug_log_path).await.unwrap();
             assert!(debug_content.contains("Hello from agent"));
@@ -204,4 +463,159 @@ mod tests {
             assert_eq!(agent.status, AgentStatus::Completed); // Should be completed for echo
         });
     }
-}
\ No newline at end of file
+    async fn spawn_sleeper(spawner: &AgentSpawner) -> String {
+        // `sh -c` forks `sleep`, so this also checks the grandchild is reached
+        spawner.spawn_agent(
+            "test-session".to_string(),
+            "worker".to_string(),
+            "Wait".to_string(),
+            "sh".to_string(),
+            vec!["-c".to_string(), "sleep 30; echo done".to_string()],
+            HashMap::new(),
+            None,
+            None,
+        ).await.expect("sh should spawn")
+    }
+
+    #[tokio::test]
+    async fn test_kill_terminates_process_group() {
+        let registry = AgentRegistry::new();
+        let temp_dir = tempdir().unwrap();
+        let spawner = AgentSpawner::new(registry.clone(), temp_dir.path().to_path_buf())
+            .with_kill_grace_period(Duration::from_millis(200));
+        let agent_id = spawn_sleeper(&spawner).await;
+        let pgid = registry.get_agent(&agent_id).unwrap().pid.unwrap();
+
+        let agent = spawner.kill_agent(&agent_id).unwrap();
+        assert_eq!(agent.status, AgentStatus::Terminated);
+
+        // Orphaned children are reaped by init, so allow it a moment
+        let mut alive = true;
+        for _ in 0..30 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            alive = process_group_alive(pgid);
+            if !alive {
+                break;
+            }
+        }
+        assert!(!alive);
+        // The exit watcher must not overwrite the terminated status
+        assert_eq!(registry.get_agent(&agent_id).unwrap().status, AgentStatus::Terminated);
+        assert!(matches!(spawner.kill_agent(&agent_id), Err(AgentControlError::InvalidState { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume() {
+        let registry = AgentRegistry::new();
+        let temp_dir = tempdir().unwrap();
+        let spawner = AgentSpawner::new(registry.clone(), temp_dir.path().to_path_buf())
+            .with_kill_grace_period(Duration::from_millis(100));
+        let agent_id = spawn_sleeper(&spawner).await;
+
+        assert!(matches!(spawner.resume_agent(&agent_id), Err(AgentControlError::InvalidState { .. })));
+        assert_eq!(spawner.pause_agent(&agent_id).unwrap().status, AgentStatus::Paused);
+        assert_eq!(spawner.resume_agent(&agent_id).unwrap().status, AgentStatus::Running);
+        assert!(matches!(spawner.pause_agent("missing"), Err(AgentControlError::NotFound(_))));
+
+        // An agent paused while waiting on a question goes back to waiting
+        registry.set_pending_interaction(&agent_id, "Proceed?".to_string()).unwrap();
+        spawner.pause_agent(&agent_id).unwrap();
+        assert_eq!(spawner.resume_agent(&agent_id).unwrap().status, AgentStatus::WaitingForInteraction);
+        // Unless the question was answered during the pause
+        spawner.pause_agent(&agent_id).unwrap();
+        let interaction_id = registry.get_agent(&agent_id).unwrap().pending_interaction.unwrap().id;
+        registry.resolve_interaction(&interaction_id, "yes".to_string()).unwrap();
+        assert_eq!(spawner.resume_agent(&agent_id).unwrap().status, AgentStatus::Running);
+
+        spawner.kill_agent(&agent_id).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8_output_does_not_stop_the_reader() {
+        let registry = AgentRegistry::new();
+        let temp_dir = tempdir().unwrap();
+        let spawner = AgentSpawner::new(registry.clone(), temp_dir.path().to_path_buf());
+        let mut events = registry.events().subscribe();
+
+        let agent_id = spawner
+            .spawn_agent(
+                "test-session".to_string(),
+                "worker".to_string(),
+                "Do the work".to_string(),
+                "printf".to_string(),
+                vec!["caf\\351\\nstill here\\n".to_string()],
+                HashMap::new(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !matches!(events.recv().await, Ok(AgentEvent::Exited { .. })) {}
+        })
+        .await
+        .expect("agent exits");
+
+        let agent = registry.get_agent(&agent_id).unwrap();
+        let lines = crate::agents::output::read_output(&spawner.debug_log_path(&agent), &agent_id, 0)
+            .await
+            .unwrap();
+        let lines: Vec<_> = lines.into_iter().map(|line| line.line).collect();
+        assert_eq!(lines, vec!["caf\u{FFFD}".to_string(), "still here".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_exit_is_noticed_while_a_child_holds_the_output_open() {
+        let registry = AgentRegistry::new();
+        let temp_dir = tempdir().unwrap();
+        let spawner = AgentSpawner::new(registry.clone(), temp_dir.path().to_path_buf());
+        let mut events = registry.events().subscribe();
+
+        let agent_id = spawner
+            .spawn_agent(
+                "test-session".to_string(),
+                "worker".to_string(),
+                "Do the work".to_string(),
+                "sh".to_string(),
+                vec!["-c".to_string(), "sleep 30 & echo done".to_string()],
+                HashMap::new(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let exited = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Ok(AgentEvent::Exited { code, .. }) = events.recv().await {
+                    return code;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(exited, Some(0));
+        let agent = registry.get_agent(&agent_id).unwrap();
+        assert_eq!(agent.status, AgentStatus::Completed);
+        signal_process_group(agent.pid.unwrap(), "KILL").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reattached_agent_gets_exit_event() {
+        let registry = AgentRegistry::new();
+        let temp_dir = tempdir().unwrap();
+        let spawner = AgentSpawner::new(registry.clone(), temp_dir.path().to_path_buf());
+        let mut child = Command::new("sleep").arg("0.5").process_group(0).spawn().unwrap();
+        let mut agent = Agent::new("test-session".to_string(), "worker".to_string());
+        agent.status = AgentStatus::Running;
+        agent.pid = child.id();
+        let agent_id = agent.id.clone();
+        registry.register_agent(agent);
+        let mut events = registry.events().subscribe();
+
+        spawner.reattach(&agent_id);
+        child.wait().await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(10), events.recv()).await.unwrap().unwrap();
+        assert!(matches!(event, AgentEvent::Exited { code: None, .. }));
+        assert!(matches!(registry.get_agent(&agent_id).unwrap().status, AgentStatus::Failed(_)));
+    }
+}
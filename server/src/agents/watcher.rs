@@ -1,22 +1,35 @@
 use std::path::{PathBuf, Path};
 use std::sync::Arc;
+use std::time::Duration;
 use notify::{Watcher, RecursiveMode, RecommendedWatcher, Config, EventKind, Event};
 use anyhow::{Result, Context};
 use tokio::fs; // For async file operations
-use tracing::{info, error}; // For logging
-use parking_lot::RwLock;
-use std::collections::HashMap;
+use tracing::{info, error, warn}; // For logging
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, HashSet};
 
+use crate::agents::events::AgentEvent;
 use crate::agents::registry::{AgentRegistry, AgentStatus};
+use crate::agents::result::{
+    clear_result_errors, write_result_errors, AgentResult, AgentResultStatus, RESULT_ERRORS_FILE,
+    RESULT_JSON_FILE,
+};
 use crate::project_sessions::ProjectSession;
 use crate::agents::dispatcher::TaskDispatcher;
 use crate::tasks::TaskGraph;
 
+/// How long a result file has to stay unchanged before it is read, so a file
+/// that is still being written is not rejected half-way.
+const RESULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Clone)]
 pub struct ResultWatcher {
     registry: AgentRegistry,
     project_sessions: Arc<RwLock<HashMap<String, ProjectSession>>>,
     base_dir: PathBuf,
     dispatcher: TaskDispatcher,
+    pending_results: Arc<Mutex<HashMap<PathBuf, u64>>>, // Result file -> latest change, for debouncing
+    accepted_agents: Arc<Mutex<HashSet<String>>>, // Agents whose result was accepted; later rewrites are ignored
 }
 
 impl ResultWatcher {
@@ -31,6 +44,8 @@ impl ResultWatcher {
             project_sessions,
             base_dir,
             dispatcher,
+            pending_results: Arc::new(Mutex::new(HashMap::new())),
+            accepted_agents: Arc::new(Mutex::new(HashSet::new())),
         }
     }
 
@@ -63,97 +78,134 @@ impl ResultWatcher {
         Ok(())
     }
 
-    async fn handle_event(&self, event: Event) -> Result<()> {
-        // Handle Modify as well, because sometimes files are created empty then modified
-        if let EventKind::Create(_) | EventKind::Modify(_) = event.kind {
-            for path in event.paths {
-                let file_name = path.file_name().and_then(|n| n.to_str());
+    /// Records an agent's final result and schedules the follow-up tasks it proposed.
+    fn accept_result(&self, session_id: &str, agent_id: &str, result: AgentResult) {
+        let status = match result.status {
+            AgentResultStatus::Success | AgentResultStatus::Partial => AgentStatus::Completed,
+            AgentResultStatus::Failed => AgentStatus::Failed(result.errors.join("; ")),
+        };
 
-                match file_name {
-                    Some("RESULT.md") => {
-                        // We only want to process this if it's not empty to avoid spamming updates
-                        // or reading empty files.
-                        if let Ok(metadata) = fs::metadata(&path).await {
-                            if metadata.len() == 0 {
-                                continue;
-                            }
-                        }
+        // Update AgentRegistry
+        if let Err(e) = self.registry.update_status_and_result(
+            agent_id,
+            status.clone(),
+            Some(result.summary.clone()),
+        ) {
+            error!("Failed to update status and result for agent {}: {}", agent_id, e);
+        }
 
-                        info!("Detected RESULT.md update: {:?}", path);
+        // Update ProjectSession
+        if let Some(session) = self.project_sessions.write().get_mut(session_id) {
+            session.latest_result = Some(result.summary.clone());
+        }
 
-                        let parent_dir = path.parent().context("RESULT.md has no parent directory")?;
-                        let agent_id = parent_dir.file_name()
-                            .context("Agent directory has no name")?
-                            .to_str()
-                            .context("Agent directory name is not valid UTF-8")?;
+        if let Some(task_graph) = result.follow_up_graph() {
+            info!("Agent {} proposed {} follow-up tasks. Dispatching...", agent_id, task_graph.tasks.len());
+            let dispatcher_clone = self.dispatcher.clone();
+            let session_id_string = session_id.to_string();
+            let requested_by = agent_id.to_string();
+            tokio::spawn(async move {
+                if let Err(e) = dispatcher_clone.dispatch_from(session_id_string, task_graph, Some(requested_by)).await {
+                    error!("Rejected TaskGraph: {}", e);
+                }
+            });
+        }
 
-                        let session_dir = parent_dir.parent().context("Agent directory has no parent")?;
-                        let session_id = session_dir.file_name()
-                            .context("Session directory has no name")?
-                            .to_str()
-                            .context("Session directory name is not valid UTF-8")?;
+        // The dispatcher picks this up and starts the next task in the queue
+        let event = match status {
+            AgentStatus::Failed(reason) => AgentEvent::Failed {
+                agent_id: agent_id.to_string(),
+                session_id: session_id.to_string(),
+                reason,
+            },
+            _ => AgentEvent::Completed {
+                agent_id: agent_id.to_string(),
+                session_id: session_id.to_string(),
+                result: Some(result.summary),
+            },
+        };
+        self.registry.events().publish(event);
+    }
 
-                        // info!("Extracted session_id: {}, agent_id: {}", session_id, agent_id);
+    /// Reads a result file once it has stopped changing for `RESULT_DEBOUNCE`.
+    fn schedule_result(&self, path: PathBuf) {
+        let change = {
+            let mut pending = self.pending_results.lock();
+            let change = pending.entry(path.clone()).or_insert(0);
+            *change += 1;
+            *change
+        };
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(RESULT_DEBOUNCE).await;
+            {
+                let mut pending = watcher.pending_results.lock();
+                if pending.get(&path) != Some(&change) {
+                    return; // Changed again; the later change reads it
+                }
+                pending.remove(&path);
+            }
+            if let Err(e) = watcher.read_result(&path).await {
+                error!("Error reading result file {:?}: {:?}", path, e);
+            }
+        });
+    }
 
-                        let result_content = fs::read_to_string(&path)
-                            .await
-                            .context(format!("Failed to read RESULT.md from {:?}", path))?;
-
-                        // Update AgentRegistry
-                        if let Err(e) = self.registry.update_status_and_result(
-                            &agent_id,
-                            AgentStatus::Completed,
-                            Some(result_content.clone()), // Clone for project_sessions
-                        ) {
-                            error!("Failed to update status and result for agent {}: {}", agent_id, e);
-                        } else {
-                            // info!("Updated status and result for agent {}", agent_id);
-                        }
+    async fn read_result(&self, path: &Path) -> Result<()> {
+        let Ok(raw) = fs::read_to_string(path).await else {
+            return Ok(()); // Removed again before it was read
+        };
+        if raw.is_empty() {
+            return Ok(());
+        }
+        let (session_id, agent_id) = agent_ids_from_result_path(path)?;
+        if self.accepted_agents.lock().contains(agent_id) {
+            info!("Ignoring {:?}: a result from agent {} was already accepted", path, agent_id);
+            return Ok(());
+        }
 
-                        // Update ProjectSession
-                        let mut project_sessions_guard = self.project_sessions.write();
-                        if let Some(session) = project_sessions_guard.get_mut(session_id) {
-                            // info!("Updating latest_result for session {}: {}", session_id, result_content);
-                            session.latest_result = Some(result_content.clone());
-                        }
-                        drop(project_sessions_guard); // Drop lock before async calls
-
-                        // Try to parse task graph from RESULT.md if it contains one
-                        // This is a fallback/heuristic if the agent writes it to RESULT.md instead of TASK_GRAPH.json
-                        if result_content.contains("\"tasks\"") {
-                            // Try extracting JSON block
-                            let json_str = if let Some(start) = result_content.find("```json") {
-                                if let Some(end) = result_content[start..].find("```") {
-                                    // Be careful with indices here, finding the SECOND ```
-                                    let block = &result_content[start..];
-                                    if let Some(end_block) = block[7..].find("```") {
-                                        &block[7..7+end_block]
-                                    } else {
-                                        ""
-                                    }
-                                } else { "" }
-                            } else {
-                                &result_content
-                            };
+        if path.file_name().and_then(|n| n.to_str()) == Some(RESULT_JSON_FILE) {
+            info!("Detected RESULT.json update: {:?}", path);
+            let agent_dir = path.parent().context("RESULT.json has no parent directory")?;
+            match AgentResult::parse(&raw) {
+                Ok(result) => {
+                    if !self.accepted_agents.lock().insert(agent_id.to_string()) {
+                        return Ok(()); // Another result file of this agent won the race
+                    }
+                    if let Err(e) = clear_result_errors(agent_dir) {
+                        warn!("Failed to remove stale {} for agent {}: {}", RESULT_ERRORS_FILE, agent_id, e);
+                    }
+                    self.accept_result(session_id, agent_id, result);
+                }
+                Err(e) => {
+                    // The agent is still running, so let it fix the file rather than failing it
+                    warn!("Rejected RESULT.json from agent {}: {}", agent_id, e);
+                    write_result_errors(agent_dir, &e)
+                        .context(format!("Failed to write {} for agent {}", RESULT_ERRORS_FILE, agent_id))?;
+                }
+            }
+        } else {
+            // Legacy fallback: a structured RESULT.json takes precedence
+            if path.with_file_name(RESULT_JSON_FILE).exists() {
+                return Ok(());
+            }
+            info!("Detected RESULT.md update: {:?}", path);
+            if !self.accepted_agents.lock().insert(agent_id.to_string()) {
+                return Ok(());
+            }
+            self.accept_result(session_id, agent_id, AgentResult::from_markdown(&raw));
+        }
+        Ok(())
+    }
 
-                            if let Ok(task_graph) = serde_json::from_str::<TaskGraph>(json_str.trim()) {
-                                info!("Detected embedded TaskGraph in RESULT.md. Dispatching...");
-                                let dispatcher_clone = self.dispatcher.clone();
-                                let session_id_string = session_id.to_string();
-                                tokio::spawn(async move {
-                                    dispatcher_clone.dispatch(session_id_string, task_graph).await;
-                                });
-                            }
-                        }
+    async fn handle_event(&self, event: Event) -> Result<()> {
+        // Handle Modify as well, because sometimes files are created empty then modified
+        if let EventKind::Create(_) | EventKind::Modify(_) = event.kind {
+            for path in event.paths {
+                let file_name = path.file_name().and_then(|n| n.to_str());
 
-                        // Notify Dispatcher that an agent has completed
-                        // This triggers the next task in the queue if this was a worker
-                        let dispatcher_clone = self.dispatcher.clone();
-                        let agent_id_string = agent_id.to_string();
-                        tokio::spawn(async move {
-                            dispatcher_clone.on_agent_complete(&agent_id_string).await;
-                        });
-                    },
+                match file_name {
+                    Some(RESULT_JSON_FILE) | Some("RESULT.md") => self.schedule_result(path),
                     Some("TASK_GRAPH.json") => {
                         info!("Detected TASK_GRAPH.json update: {:?}", path);
                         
@@ -179,8 +231,11 @@ impl ResultWatcher {
                                 info!("Parsed TaskGraph with {} tasks. Dispatching...", task_graph.tasks.len());
                                 let dispatcher_clone = self.dispatcher.clone();
                                 let session_id_string = session_id.to_string();
+                                let requested_by = parent_dir.file_name().map(|name| name.to_string_lossy().to_string());
                                 tokio::spawn(async move {
-                                    dispatcher_clone.dispatch(session_id_string, task_graph).await;
+                                    if let Err(e) = dispatcher_clone.dispatch_from(session_id_string, task_graph, requested_by).await {
+                                        error!("Rejected TaskGraph: {}", e);
+                                    }
                                 });
                             },
                             Err(e) => error!("Failed to parse TASK_GRAPH.json: {}", e),
@@ -192,4 +247,20 @@ impl ResultWatcher {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Extracts `(session_id, agent_id)` from `.vibe/agents/<session>/<agent>/<file>`.
+fn agent_ids_from_result_path(path: &Path) -> Result<(&str, &str)> {
+    let agent_dir = path.parent().context("Result file has no parent directory")?;
+    let agent_id = agent_dir.file_name()
+        .context("Agent directory has no name")?
+        .to_str()
+        .context("Agent directory name is not valid UTF-8")?;
+
+    let session_dir = agent_dir.parent().context("Agent directory has no parent")?;
+    let session_id = session_dir.file_name()
+        .context("Session directory has no name")?
+        .to_str()
+        .context("Session directory name is not valid UTF-8")?;
+    Ok((session_id, agent_id))
+}
@@ -0,0 +1,244 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::agents::merge::MergeOutcome;
+use crate::utils::process::spawn_and_capture_output;
+
+/// Prefix of the branches workers commit to, e.g. `vibe/<session>/init-1`.
+pub const WORKER_BRANCH_PREFIX: &str = "vibe/";
+
+/// Where worker processes run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerIsolation {
+    /// In the agent's directory under the server root.
+    #[default]
+    None,
+    /// In a dedicated `git worktree` of the project, on a branch per task.
+    Worktree,
+}
+
+impl std::str::FromStr for WorkerIsolation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "none" | "" => Ok(WorkerIsolation::None),
+            "worktree" => Ok(WorkerIsolation::Worktree),
+            other => Err(format!("unknown worker isolation mode '{}'", other)),
+        }
+    }
+}
+
+/// A worker's checkout of the project, as recorded on its `Agent`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorktreeInfo {
+    pub project_root: PathBuf,
+    pub path: PathBuf,
+    pub branch: String,
+    pub base_commit: String,
+    pub diff_stat: Option<String>, // Filled in once the worker has finished
+    #[serde(default)]
+    pub merge: Option<MergeOutcome>, // Last attempt to merge the branch into the session
+    #[serde(default)]
+    pub removed: bool, // The worktree and branch were deleted once no longer needed
+}
+
+impl WorktreeInfo {
+    /// Markdown section describing the branch and what changed on it.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("## Worktree\n\nBranch: `{}` (from `{}`)\n", self.branch, self.base_commit);
+        if let Some(stat) = self.diff_stat.as_deref().filter(|s| !s.is_empty()) {
+            summary.push_str(&format!("\n```\n{}\n```\n", stat));
+        } else {
+            summary.push_str("\nNo changes.\n");
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WorktreeError {
+    #[error("{0} is not inside a git repository")]
+    NotARepository(PathBuf),
+    #[error(transparent)]
+    Git(#[from] io::Error),
+}
+
+/// Branch name for attempt `attempt` of `task_id` in `session_id`. Task ids
+/// are only unique within a session, so the session is part of the name.
+/// Characters git does not allow in ref names are replaced.
+pub fn worker_branch(session_id: &str, task_id: &str, attempt: u32) -> String {
+    let branch = format!("{}{}/{}", WORKER_BRANCH_PREFIX, ref_slug(session_id), ref_slug(task_id));
+    if attempt > 1 {
+        format!("{}-attempt-{}", branch, attempt)
+    } else {
+        branch
+    }
+}
+
+fn ref_slug(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect()
+}
+
+/// Adds a worktree of `project_root` at `path` on a new `branch` cut from HEAD.
+pub fn create_worktree(project_root: &Path, path: &Path, branch: &str) -> Result<WorktreeInfo, WorktreeError> {
+    let root = project_root.to_string_lossy();
+    let base_commit = match spawn_and_capture_output("git", &["-C", &root, "rev-parse", "HEAD"]) {
+        Ok((stdout, _)) => stdout.trim().to_string(),
+        Err(_) => return Err(WorktreeError::NotARepository(project_root.to_path_buf())),
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    spawn_and_capture_output(
+        "git",
+        &["-C", &root, "worktree", "add", "-b", branch, &path.to_string_lossy(), &base_commit],
+    )?;
+
+    Ok(WorktreeInfo {
+        project_root: project_root.to_path_buf(),
+        path: path.to_path_buf(),
+        branch: branch.to_string(),
+        base_commit,
+        diff_stat: None,
+        merge: None,
+        removed: false,
+    })
+}
+
+/// Removes a worker's worktree, uncommitted changes included, and deletes its
+/// branch. Used once the branch is merged or the worker never started.
+pub fn remove_worktree(worktree: &WorktreeInfo) -> Result<(), WorktreeError> {
+    let root = worktree.project_root.to_string_lossy();
+    spawn_and_capture_output(
+        "git",
+        &["-C", &root, "worktree", "remove", "--force", &worktree.path.to_string_lossy()],
+    )?;
+    spawn_and_capture_output("git", &["-C", &root, "branch", "-D", &worktree.branch])?;
+    Ok(())
+}
+
+/// Summarises everything the worker changed since the worktree was created,
+/// whether committed or not, including new files.
+pub fn diff_stat(worktree: &WorktreeInfo) -> Result<String, WorktreeError> {
+    let stdout = diff_from_base(worktree, &["--stat"])?;
+    Ok(stdout.trim_end().to_string())
+}
+
+/// Paths, relative to the worktree, that differ from the base commit.
+pub fn changed_files(worktree: &WorktreeInfo) -> Result<Vec<String>, WorktreeError> {
+    // Without renames a moved file shows up under its old path as well as its new one
+    let stdout = diff_from_base(worktree, &["--name-only", "--no-renames"])?;
+    Ok(stdout.lines().map(str::to_string).collect())
+}
+
+/// Runs `git diff <options>` from the base commit to the worktree's files.
+/// Untracked files are staged in a scratch copy of the index, so they show up
+/// without touching the index the worker uses.
+fn diff_from_base(worktree: &WorktreeInfo, options: &[&str]) -> Result<String, WorktreeError> {
+    let path = worktree.path.to_string_lossy();
+    let (index, _) = spawn_and_capture_output("git", &["-C", &path, "rev-parse", "--git-path", "index"])?;
+    let index = worktree.path.join(index.trim());
+    let scratch = std::env::temp_dir().join(format!("vibe-index-{}", uuid::Uuid::new_v4()));
+    match std::fs::copy(&index, &scratch) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+
+    let mut diff_args = vec!["diff", "--cached"];
+    diff_args.extend_from_slice(options);
+    diff_args.push(&worktree.base_commit);
+    let diff = git_with_index(&path, &scratch, &["add", "--all"]).and_then(|_| git_with_index(&path, &scratch, &diff_args));
+    let _ = std::fs::remove_file(&scratch);
+    Ok(diff?)
+}
+
+fn git_with_index(dir: &str, index: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).env("GIT_INDEX_FILE", index).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "Command 'git {}' failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Creates a repository with one commit.
+    pub(crate) fn init_repo(root: &Path) {
+        let root = root.to_string_lossy();
+        for args in [
+            vec!["init", "-q", "-b", "main"],
+            vec!["config", "user.email", "dev@example.com"],
+            vec!["config", "user.name", "Dev"],
+        ] {
+            let mut full = vec!["-C", &root];
+            full.extend(args);
+            spawn_and_capture_output("git", &full).unwrap();
+        }
+        std::fs::write(Path::new(&*root).join("README.md"), "hello\n").unwrap();
+        spawn_and_capture_output("git", &["-C", &root, "add", "README.md"]).unwrap();
+        spawn_and_capture_output("git", &["-C", &root, "commit", "-q", "-m", "init"]).unwrap();
+    }
+
+    #[test]
+    fn test_worker_branch_names() {
+        assert_eq!(worker_branch("s1", "init-1", 1), "vibe/s1/init-1");
+        assert_eq!(worker_branch("s1", "fix docs/readme", 2), "vibe/s1/fix-docs-readme-attempt-2");
+        assert_ne!(worker_branch("s1", "init-1", 1), worker_branch("s2", "init-1", 1));
+    }
+
+    #[test]
+    fn test_worktree_diff_stat_includes_new_files() {
+        let dir = tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        init_repo(&repo);
+
+        let info = create_worktree(&repo, &dir.path().join("worktrees").join("agent-1"), "vibe/t1").unwrap();
+        assert!(info.path.join("README.md").exists());
+
+        std::fs::write(info.path.join("README.md"), "hello\nworld\n").unwrap();
+        std::fs::write(info.path.join("NEW.md"), "new\n").unwrap();
+        let stat = diff_stat(&info).unwrap();
+        assert!(stat.contains("README.md"));
+        assert!(stat.contains("NEW.md"));
+        assert!(stat.contains("2 files changed"));
+        // Inspecting the changes leaves the worker's index alone
+        let (status, _) = spawn_and_capture_output("git", &["-C", &info.path.to_string_lossy(), "status", "--porcelain"]).unwrap();
+        assert!(status.contains("?? NEW.md"));
+        assert_eq!(changed_files(&info).unwrap(), vec!["NEW.md".to_string(), "README.md".to_string()]);
+
+        let summary = WorktreeInfo { diff_stat: Some(stat), ..info.clone() }.summary();
+        assert!(summary.contains("Branch: `vibe/t1`"));
+        assert!(summary.contains("NEW.md"));
+
+        remove_worktree(&info).unwrap();
+        assert!(!info.path.exists());
+        let branch = spawn_and_capture_output("git", &["-C", &repo.to_string_lossy(), "branch", "--list", "vibe/t1"]);
+        assert!(branch.unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn test_non_repository_is_rejected() {
+        let dir = tempdir().unwrap();
+        let err = create_worktree(dir.path(), &dir.path().join("wt"), "vibe/t1").unwrap_err();
+        assert!(matches!(err, WorktreeError::NotARepository(_)));
+    }
+}
@@ -1,26 +1,36 @@
 use axum::{
     body::Body,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{Request, StatusCode},
     middleware::{from_fn_with_state, Next},
     response::Response,
     routing::{get, post},
     Json, Router,
 };
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use crate::agents::spawner::AgentSpawner;
+use crate::agents::events::AgentEvent;
+use crate::agents::merge::{MergeError, MergeReport};
+use crate::agents::output::{read_output, OutputLine};
+use crate::agents::spawner::{AgentControlError, AgentSpawner};
+use crate::prompts::{orchestrator_prompt, task_prompt, ResolvedPrompt};
+use crate::tasks::{Task, TaskGraph};
 
 use crate::{
     global_registry::GlobalProjectRegistry,
     llm::ProviderKind,
-    profiles::ProfileSummary,
+    profiles::{InvalidProfile, ProfileSummary},
     project_sessions::{
-        create_or_get_session_for_project, list_sessions as list_project_sessions, ProjectSession,
+        create_or_get_session_for_project, get_session as get_project_session,
+        list_sessions as list_project_sessions, refresh_usage as refresh_project_usage,
+        ProjectSession,
     },
-    sessions::{SessionCreateParams, SessionDetail, SessionSummary, WsEvent},
+    sessions::{SessionCreateParams, SessionDetail, SessionSummary},
     state::AppState,
-    agents::registry::{AgentStatus, AgentRegistry, Interaction}, // Added AgentRegistry for AgentStatus and registry methods
+    agents::interaction::InteractionKind,
+    agents::registry::{Agent, AgentNode, AgentStatus, Interaction, InteractionStatus},
+    usage::TokenUsage,
 };
 use std::{collections::HashMap, env, str::FromStr};
 use tracing::{info, error}; // Added for logging in handlers
@@ -34,17 +44,30 @@ pub fn router(state: AppState) -> Router {
             "/project-sessions",
             get(list_project_sessions_http).post(create_project_session),
         )
+        .route("/project-sessions/:id/prompt", get(preview_prompt))
         .route("/sessions", get(list_sessions).post(create_session))
         .route("/sessions/:id", get(get_session).delete(delete_session))
+        .route("/sessions/:id/usage", get(get_session_usage))
         .route("/profiles", get(list_profiles))
         .route("/debug/spawn", post(debug_spawn_agent))
         // --- New routes for agent communication ---
         .route("/agent/report", post(handle_agent_report))
         .route("/agent/complete", post(handle_agent_complete))
+        .route("/agent/spawn", post(handle_agent_spawn))
         .route("/agent/ask", post(handle_agent_ask))
         .route("/agent/ask/:id", get(handle_agent_ask_status))
+        .route("/agent/ask/:id/wait", get(handle_agent_ask_wait))
         .route("/agent/interactions/pending", get(handle_list_pending_interactions))
         .route("/interactions/:id/reply", post(handle_interaction_reply))
+        .route("/agents", get(list_agents))
+        .route("/agents/:id", get(get_agent))
+        .route("/sessions/:id/agents", get(list_session_agents))
+        .route("/sessions/:id/agents/tree", get(session_agent_tree))
+        .route("/agents/:id/kill", post(kill_agent))
+        .route("/agents/:id/pause", post(pause_agent))
+        .route("/agents/:id/resume", post(resume_agent))
+        .route("/agents/:id/merge", post(merge_agent))
+        .route("/agents/:id/log", get(agent_log))
         .with_state(state.clone())
         .layer(from_fn_with_state(state, guard_shared_secret))
 }
@@ -99,7 +122,8 @@ async fn debug_spawn_agent(
             command,
             args,
             env_vars,
-            None // No ID override for debug spawn
+            None, // No ID override for debug spawn
+            None,
         ).await // Await here
         .map_err(|e| {
             tracing::error!("Failed to spawn agent: {}", e);
@@ -115,6 +139,13 @@ async fn handle_agent_report(
 ) -> StatusCode {
     info!("Agent Report: {:?}", payload);
 
+    if let Some(usage) = payload.usage {
+        if let Err(e) = state.agents.add_usage(&payload.agent_id, usage) {
+            error!("Failed to record agent usage: {}", e);
+        }
+        refresh_project_usage(&state, &payload.session_id);
+    }
+
     if let Err(e) = state.agents.update_status_and_progress(
         &payload.agent_id,
         AgentStatus::Running, // Agents are always "Running" when they report.
@@ -124,17 +155,12 @@ async fn handle_agent_report(
         error!("Failed to update agent status for report: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     } else {
-        state.sessions.publish(
-            &payload.session_id,
-            WsEvent::AgentStatusUpdate {
-                session_id: payload.session_id.clone(),
-                agent_id: payload.agent_id.clone(),
-                status: "running".to_string(),
-                progress: payload.progress,
-                thought: payload.thought.clone(),
-                result: None,
-            }
-        ).await;
+        state.agents.events().publish(AgentEvent::Reported {
+            agent_id: payload.agent_id,
+            session_id: payload.session_id,
+            progress: payload.progress,
+            thought: payload.thought,
+        });
         StatusCode::OK
     }
 }
@@ -153,44 +179,231 @@ async fn handle_agent_complete(
         error!("Failed to update agent status for completion: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     } else {
-        state.sessions.publish(
-            &payload.session_id,
-            WsEvent::AgentStatusUpdate {
-                session_id: payload.session_id.clone(),
-                agent_id: payload.agent_id.clone(),
-                status: "completed".to_string(),
-                progress: 100,
-                thought: None,
-                result: payload.result_summary.clone(),
-            }
-        ).await;
+        state.agents.events().publish(AgentEvent::Completed {
+            agent_id: payload.agent_id,
+            session_id: payload.session_id,
+            result: payload.result_summary,
+        });
         StatusCode::OK
     }
 }
 
+/// All agents, optionally only those with the given status label
+/// (e.g. `running`, `waiting_for_interaction`).
+async fn list_agents(
+    State(state): State<AppState>,
+    Query(query): Query<AgentListQuery>,
+) -> Json<AgentListResponse> {
+    let agents = state
+        .agents
+        .list_agents()
+        .into_iter()
+        .filter(|agent| query.status.as_deref().is_none_or(|status| agent.status.label() == status))
+        .map(|agent| agent_detail(&state, agent))
+        .collect();
+    Json(AgentListResponse { agents })
+}
+
+async fn list_session_agents(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Json<AgentListResponse> {
+    let mut agents = state.agents.list_agents_by_session(&id);
+    agents.sort_by_key(|agent| agent.started_at);
+    let agents = agents.into_iter().map(|agent| agent_detail(&state, agent)).collect();
+    Json(AgentListResponse { agents })
+}
+
+async fn session_agent_tree(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Json<AgentTreeResponse> {
+    let mut agents = state.agents.agent_tree(&id);
+    fill_task_ids(&state, &mut agents);
+    Json(AgentTreeResponse { agents })
+}
+
+/// Agents restored from snapshots older than `Agent::task_id` only have
+/// their task in the dispatcher's schedule.
+fn fill_task_id(state: &AppState, agent: &mut Agent) {
+    if agent.task_id.is_none() {
+        agent.task_id = state.dispatcher.task_of_agent(&agent.session_id, &agent.id);
+    }
+}
+
+fn fill_task_ids(state: &AppState, nodes: &mut [AgentNode]) {
+    for node in nodes {
+        fill_task_id(state, &mut node.agent);
+        fill_task_ids(state, &mut node.children);
+    }
+}
+
+async fn get_agent(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<AgentDetail>, StatusCode> {
+    let agent = state.agents.get_agent(&id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(agent_detail(&state, agent)))
+}
+
+fn agent_detail(state: &AppState, mut agent: Agent) -> AgentDetail {
+    fill_task_id(state, &mut agent);
+    AgentDetail {
+        status_label: agent.status.label(),
+        agent,
+    }
+}
+
+async fn kill_agent(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Agent>, StatusCode> {
+    agent_control_response(state.agent_spawner.kill_agent(&id))
+}
+
+async fn pause_agent(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Agent>, StatusCode> {
+    agent_control_response(state.agent_spawner.pause_agent(&id))
+}
+
+async fn resume_agent(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Agent>, StatusCode> {
+    agent_control_response(state.agent_spawner.resume_agent(&id))
+}
+
+/// Merges the agent's worktree branch into its session's integration branch.
+/// A conflict is still a 200: the report says how it is being resolved.
+async fn merge_agent(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<MergeReport>, StatusCode> {
+    match state.dispatcher.merge_agent(&id).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Merge request failed: {}", e);
+            Err(match e {
+                MergeError::NotFound(_) => StatusCode::NOT_FOUND,
+                MergeError::NoWorktree(_) | MergeError::StillRunning(_) => StatusCode::CONFLICT,
+                MergeError::Worktree(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            })
+        }
+    }
+}
+
+/// Output the agent produced from line `from` on, to backfill a live view
+/// before following `AgentOutput` events.
+async fn agent_log(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<AgentLogQuery>,
+) -> Result<Json<AgentLogResponse>, StatusCode> {
+    let agent = state.agents.get_agent(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let path = state.agent_spawner.debug_log_path(&agent);
+    let from = query.from.unwrap_or(0);
+    let lines = read_output(&path, &id, from).await.map_err(|e| {
+        error!("Failed to read output of agent {}: {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let next_seq = lines.last().map_or(from, |line| line.seq + 1);
+    Ok(Json(AgentLogResponse {
+        agent_id: id,
+        lines,
+        next_seq,
+    }))
+}
+
+/// Maps a control error to a status code; the spawner broadcasts the status change.
+fn agent_control_response(result: Result<Agent, AgentControlError>) -> Result<Json<Agent>, StatusCode> {
+    match result {
+        Ok(agent) => Ok(Json(agent)),
+        Err(e) => {
+            error!("Agent control request failed: {}", e);
+            Err(match e {
+                AgentControlError::NotFound(_) => StatusCode::NOT_FOUND,
+                AgentControlError::InvalidState { .. } | AgentControlError::NoProcess(_) => StatusCode::CONFLICT,
+                AgentControlError::Signal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            })
+        }
+    }
+}
+
+/// Queues a task on behalf of an agent. The agent that picks it up becomes
+/// the requesting agent's child.
+async fn handle_agent_spawn(
+    State(state): State<AppState>,
+    Json(payload): Json<AgentSpawnPayload>,
+) -> Result<Json<AgentSpawnResponse>, (StatusCode, String)> {
+    info!("Agent Spawn: {:?}", payload);
+    let parent = state
+        .agents
+        .get_agent(&payload.agent_id)
+        .filter(|agent| agent.session_id == payload.session_id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("agent {} not found in session", payload.agent_id)))?;
+
+    let task = Task {
+        agent_type: payload.agent_type,
+        depends_on: payload.depends_on,
+        must_touch: payload.must_touch,
+        may_touch: payload.may_touch,
+        ..Task::new(
+            format!("sub-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            payload.description,
+        )
+    };
+    let task_id = task.id.clone();
+    state
+        .dispatcher
+        .dispatch_from(parent.session_id, TaskGraph { tasks: vec![task] }, Some(parent.id))
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(AgentSpawnResponse { task_id }))
+}
+
 async fn handle_agent_ask(
     State(state): State<AppState>,
     Json(payload): Json<AgentAskPayload>,
-) -> Result<Json<AgentAskResponse>, StatusCode> {
+) -> Result<Json<AgentAskResponse>, (StatusCode, String)> {
     info!("Agent Ask: {:?}", payload);
-    match state.agents.set_pending_interaction(&payload.agent_id, payload.question) {
+    payload.kind.validate().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let default_answer = payload
+        .default_answer
+        .as_deref()
+        .map(|answer| payload.kind.normalize_answer(answer))
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid default answer: {}", e)))?;
+    let deadline = payload
+        .timeout_secs
+        .map(|secs| {
+            i64::try_from(secs)
+                .ok()
+                .and_then(TimeDelta::try_seconds)
+                .and_then(|timeout| Utc::now().checked_add_signed(timeout))
+                .ok_or((StatusCode::BAD_REQUEST, format!("timeout_secs {} is too large", secs)))
+        })
+        .transpose()?;
+    let interaction = Interaction::new(payload.question.clone())
+        .with_kind(payload.kind)
+        .with_default_answer(default_answer)
+        .with_deadline(deadline);
+    let deadline = interaction.deadline;
+
+    match state.agents.raise_interaction(&payload.agent_id, interaction) {
         Ok(interaction_id) => {
-            state.sessions.publish(
-                &payload.session_id,
-                WsEvent::AgentStatusUpdate {
-                    session_id: payload.session_id.clone(),
-                    agent_id: payload.agent_id.clone(),
-                    status: "waiting_for_interaction".to_string(),
-                    progress: 0, // Can be improved
-                    thought: Some("Waiting for user input...".to_string()),
-                    result: None,
-                }
-            ).await;
-            Ok(Json(AgentAskResponse { interaction_id }))
+            state.agents.events().publish(AgentEvent::Asked {
+                agent_id: payload.agent_id,
+                session_id: payload.session_id,
+                interaction_id: interaction_id.clone(),
+                question: payload.question,
+            });
+            Ok(Json(AgentAskResponse { interaction_id, deadline }))
         },
         Err(e) => {
             error!("Failed to set interaction: {}", e);
-            Err(StatusCode::NOT_FOUND)
+            Err((StatusCode::NOT_FOUND, e))
         }
     }
 }
@@ -199,6 +412,8 @@ async fn handle_agent_ask_status(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> Result<Json<Interaction>, StatusCode> {
+    // Deadlines are also enforced by the watchdog, but pollers should not wait for it
+    state.dispatcher.expire_interactions();
     if let Some(interaction) = state.agents.get_interaction_status(&id) {
         Ok(Json(interaction))
     } else {
@@ -206,17 +421,49 @@ async fn handle_agent_ask_status(
     }
 }
 
+/// Long-polls an interaction: answers as soon as it is resolved or expires,
+/// or with its still-pending state once `timeout` seconds pass.
+async fn handle_agent_ask_wait(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<AgentAskWaitQuery>,
+) -> Result<Json<Interaction>, StatusCode> {
+    state.dispatcher.expire_interactions();
+    let timeout = query.timeout.unwrap_or(DEFAULT_ASK_WAIT_SECS).min(MAX_ASK_WAIT_SECS);
+    state
+        .agents
+        .wait_for_interaction(&id, std::time::Duration::from_secs(timeout))
+        .await
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // The wait also ends at the interaction's deadline, which expires it
+    state.dispatcher.expire_interactions();
+    state.agents.get_interaction_status(&id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
 async fn handle_interaction_reply(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(payload): Json<InteractionReplyPayload>,
-) -> StatusCode {
+) -> Result<StatusCode, (StatusCode, String)> {
     info!("Interaction Reply: {:?}", id);
-    match state.agents.resolve_interaction(&id, payload.answer) {
-        Ok(_) => StatusCode::OK,
+    let interaction = state
+        .agents
+        .get_interaction_status(&id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("interaction {} not found", id)))?;
+    let answer = interaction
+        .kind
+        .normalize_answer(&payload.answer)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    match state.agents.resolve_interaction(&id, answer) {
+        Ok(_) => Ok(StatusCode::OK),
         Err(e) => {
             error!("Failed to resolve interaction: {}", e);
-            StatusCode::NOT_FOUND
+            let status = if interaction.status == InteractionStatus::Pending {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::CONFLICT
+            };
+            Err((status, e))
         }
     }
 }
@@ -238,6 +485,33 @@ async fn list_project_sessions_http(
     Json(ProjectSessionListResponse { sessions })
 }
 
+/// Shows the instruction an agent of the given type (or the agent for a
+/// scheduled task) would receive in this project session.
+async fn preview_prompt(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<PromptPreviewQuery>,
+) -> Result<Json<ResolvedPrompt>, StatusCode> {
+    let session = get_project_session(&state, &id).ok_or(StatusCode::NOT_FOUND)?;
+    let profile = state.config.prompt_profile.as_deref().and_then(|id| state.profiles.get(id));
+    let agent_id = "<agent-id>";
+
+    let task = match &query.task {
+        Some(task_id) => {
+            let schedule = state.dispatcher.schedule_snapshot(&id).ok_or(StatusCode::NOT_FOUND)?;
+            schedule.get(task_id).ok_or(StatusCode::NOT_FOUND)?.task.clone()
+        }
+        None if query.agent_type.as_deref() == Some("orchestrator") => {
+            return Ok(Json(orchestrator_prompt(profile.as_ref(), &session, agent_id)));
+        }
+        None => Task {
+            agent_type: query.agent_type.clone(),
+            ..Task::new("<task-id>".to_string(), "<task description>".to_string())
+        },
+    };
+    Ok(Json(task_prompt(profile.as_ref(), &id, Some(&session), agent_id, &task, None)))
+}
+
 async fn get_session(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -250,6 +524,29 @@ async fn get_session(
     Ok(Json(SessionDetailResponse { session }))
 }
 
+async fn get_session_usage(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<SessionUsageResponse>, StatusCode> {
+    // Chat sessions carry their own model; project sessions run on the server default.
+    let (model, usage) = if let Some(summary) = state.sessions.summary(&id).await {
+        (summary.llm_config.model, summary.usage)
+    } else if let Some(session) = get_project_session(&state, &id) {
+        (state.config.default_llm.model.clone(), session.usage)
+    } else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let estimated_cost_usd = state.config.price_table.estimate_cost(&model, &usage);
+    Ok(Json(SessionUsageResponse {
+        session_id: id,
+        model,
+        total_tokens: usage.total_tokens(),
+        usage,
+        estimated_cost_usd,
+    }))
+}
+
 async fn delete_session(State(state): State<AppState>, Path(id): Path<String>) -> StatusCode {
     if state.sessions.delete(&id).await {
         StatusCode::NO_CONTENT
@@ -273,6 +570,7 @@ async fn create_session(
                 llm_config.provider = parsed;
             }
         }
+        llm_config.provider = llm_config.provider.with_base_url(config.base_url);
         if let Some(model) = config.model {
             llm_config.model = model;
         }
@@ -324,6 +622,7 @@ async fn create_project_session(
 async fn list_profiles(State(state): State<AppState>) -> Json<ProfileListResponse> {
     Json(ProfileListResponse {
         profiles: state.profiles.summaries(),
+        invalid: state.profiles.invalid(),
     })
 }
 
@@ -356,9 +655,20 @@ struct SessionDetailResponse {
     session: SessionDetail,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionUsageResponse {
+    session_id: String,
+    model: String,
+    usage: TokenUsage,
+    total_tokens: u64,
+    estimated_cost_usd: Option<f64>,
+}
+
 #[derive(Serialize)]
 struct ProfileListResponse {
     profiles: Vec<ProfileSummary>,
+    invalid: Vec<InvalidProfile>, // Profiles skipped because they failed to load
 }
 
 #[derive(Serialize)]
@@ -398,6 +708,56 @@ struct LlmConfigPayload {
     provider: Option<String>,
     model: Option<String>,
     temperature: Option<f32>,
+    base_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AgentListQuery {
+    status: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AgentListResponse {
+    agents: Vec<AgentDetail>,
+}
+
+#[derive(Serialize)]
+struct AgentDetail {
+    #[serde(flatten)]
+    agent: Agent,
+    status_label: &'static str,
+}
+
+#[derive(Serialize)]
+struct AgentTreeResponse {
+    agents: Vec<AgentNode>, // Session roots, each with its descendants
+}
+
+/// Seconds `/agent/ask/:id/wait` holds a request open by default, and at most.
+const DEFAULT_ASK_WAIT_SECS: u64 = 30;
+const MAX_ASK_WAIT_SECS: u64 = 120;
+
+#[derive(Deserialize)]
+struct AgentAskWaitQuery {
+    timeout: Option<u64>, // Seconds
+}
+
+#[derive(Deserialize)]
+struct AgentLogQuery {
+    from: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct AgentLogResponse {
+    agent_id: String,
+    lines: Vec<OutputLine>,
+    next_seq: u64, // Pass as `from` to fetch only newer lines
+}
+
+#[derive(Deserialize)]
+struct PromptPreviewQuery {
+    agent_type: Option<String>, // Defaults to "worker"
+    task: Option<String>,       // Id of a scheduled task to preview instead
 }
 
 #[derive(Deserialize)]
@@ -426,6 +786,8 @@ pub struct AgentReportPayload {
     pub session_id: String,
     pub progress: u8,
     pub thought: Option<String>,
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -435,16 +797,43 @@ pub struct AgentCompletePayload {
     pub result_summary: Option<String>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct AgentSpawnPayload {
+    pub agent_id: String, // The requesting agent
+    pub session_id: String,
+    pub description: String,
+    #[serde(default)]
+    pub agent_type: Option<String>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub must_touch: Vec<String>,
+    #[serde(default)]
+    pub may_touch: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct AgentSpawnResponse {
+    pub task_id: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct AgentAskPayload {
     pub agent_id: String,
     pub session_id: String,
     pub question: String,
+    #[serde(default)]
+    pub kind: InteractionKind,
+    #[serde(default)]
+    pub default_answer: Option<String>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>, // Without one the question waits for an answer indefinitely
 }
 
 #[derive(Serialize)]
 pub struct AgentAskResponse {
     pub interaction_id: String,
+    pub deadline: Option<DateTime<Utc>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -2,7 +2,16 @@ use std::{env, net::IpAddr, path::PathBuf, str::FromStr};
 
 use serde::Serialize;
 
-use crate::llm::{LlmConfig, ProviderKind};
+use crate::{
+    agents::{
+        boundaries::BoundaryPolicy,
+        merge::{ConflictPolicy, MergeMode},
+        worktree::WorkerIsolation,
+    },
+    llm::{LlmConfig, ProviderKind},
+    tasks::RetryPolicy,
+    usage::PriceTable,
+};
 
 #[derive(Clone, Serialize)]
 pub struct ServerConfig {
@@ -11,7 +20,19 @@ pub struct ServerConfig {
     pub ws_port: u16,
     pub shared_secret: Option<String>,
     pub prompt_profile_dir: PathBuf,
+    pub prompt_profile: Option<String>, // Profile layered into agent instructions; None uses project files only
     pub default_llm: LlmConfig,
+    pub max_workers: usize,
+    pub max_workers_per_session: usize,
+    pub price_table: PriceTable,
+    pub agent_timeout_secs: u64,      // Wall-clock limit per worker; 0 disables it
+    pub agent_idle_timeout_secs: u64, // Limit without reports or output; 0 disables it
+    pub task_max_attempts: u32,
+    pub task_retry_backoff_secs: u64,
+    pub worker_isolation: WorkerIsolation,
+    pub merge_mode: MergeMode,
+    pub merge_conflict_policy: ConflictPolicy,
+    pub boundary_policy: BoundaryPolicy,
 }
 
 impl ServerConfig {
@@ -20,11 +41,31 @@ impl ServerConfig {
         let http_port = read_env("AGENT_HUB_HTTP_PORT").unwrap_or_else(|| "4110".into());
         let ws_port = read_env("AGENT_HUB_WS_PORT").unwrap_or_else(|| "4111".into());
         let protocol = read_env("AGENT_HUB_PROVIDER").unwrap_or_else(|| "dummy".into());
+        let provider_base_url = read_env("AGENT_HUB_PROVIDER_BASE_URL");
         let model = read_env("AGENT_HUB_MODEL").unwrap_or_else(|| "gemini-2.0-flash".into()); // Changed from dummy-orchestrator
         let temperature = read_env("AGENT_HUB_TEMPERATURE")
             .and_then(|value| value.parse::<f32>().ok())
             .unwrap_or(0.2);
         let shared_secret = read_env("AGENT_HUB_SECRET");
+        let max_workers = read_env("AGENT_HUB_MAX_WORKERS").unwrap_or_else(|| "4".into());
+        let max_workers_per_session =
+            read_env("AGENT_HUB_MAX_WORKERS_PER_SESSION").unwrap_or_else(|| "2".into());
+        let agent_timeout_secs =
+            read_env("AGENT_HUB_AGENT_TIMEOUT_SECS").unwrap_or_else(|| "3600".into());
+        let agent_idle_timeout_secs =
+            read_env("AGENT_HUB_AGENT_IDLE_TIMEOUT_SECS").unwrap_or_else(|| "600".into());
+        let task_max_attempts =
+            read_env("AGENT_HUB_TASK_MAX_ATTEMPTS").unwrap_or_else(|| "2".into());
+        let task_retry_backoff_secs =
+            read_env("AGENT_HUB_TASK_RETRY_BACKOFF_SECS").unwrap_or_else(|| "30".into());
+        let worker_isolation = read_env("AGENT_HUB_WORKER_ISOLATION").unwrap_or_else(|| "none".into());
+        let merge_mode = read_env("AGENT_HUB_MERGE_MODE").unwrap_or_else(|| "manual".into());
+        let merge_conflict_policy = read_env("AGENT_HUB_MERGE_CONFLICTS").unwrap_or_else(|| "ask".into());
+        let boundary_policy = read_env("AGENT_HUB_BOUNDARY_POLICY").unwrap_or_else(|| "report".into());
+        let price_table = match read_env("AGENT_HUB_PRICE_TABLE") {
+            Some(path) => PriceTable::load(path)?,
+            None => PriceTable::default(),
+        };
         let prompt_dir = PathBuf::from(
             read_env("AGENT_HUB_PROMPT_PROFILE_DIR").unwrap_or_else(|| "prompts/profiles".into()),
         );
@@ -35,14 +76,39 @@ impl ServerConfig {
             ws_port: ws_port.parse()?,
             shared_secret,
             prompt_profile_dir: prompt_dir,
+            prompt_profile: read_env("AGENT_HUB_PROMPT_PROFILE"),
             default_llm: LlmConfig {
-                provider: ProviderKind::from_str(&protocol).unwrap_or(ProviderKind::Dummy),
+                provider: ProviderKind::from_str(&protocol)
+                    .unwrap_or(ProviderKind::Dummy)
+                    .with_base_url(provider_base_url),
                 model,
                 temperature,
             },
+            max_workers: parse_worker_limit("AGENT_HUB_MAX_WORKERS", &max_workers)?,
+            max_workers_per_session: parse_worker_limit(
+                "AGENT_HUB_MAX_WORKERS_PER_SESSION",
+                &max_workers_per_session,
+            )?,
+            price_table,
+            agent_timeout_secs: agent_timeout_secs.parse()?,
+            agent_idle_timeout_secs: agent_idle_timeout_secs.parse()?,
+            task_max_attempts: task_max_attempts.parse()?,
+            task_retry_backoff_secs: task_retry_backoff_secs.parse()?,
+            worker_isolation: worker_isolation.parse().map_err(anyhow::Error::msg)?,
+            merge_mode: merge_mode.parse().map_err(anyhow::Error::msg)?,
+            merge_conflict_policy: merge_conflict_policy.parse().map_err(anyhow::Error::msg)?,
+            boundary_policy: boundary_policy.parse().map_err(anyhow::Error::msg)?,
         })
     }
 
+    /// Retry policy for tasks that do not set their own.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.task_max_attempts.max(1),
+            backoff_secs: self.task_retry_backoff_secs,
+        }
+    }
+
     pub fn authorize(&self, header_value: Option<&str>) -> bool {
         match (&self.shared_secret, header_value) {
             (Some(expected), Some(actual)) => expected == actual,
@@ -52,6 +118,14 @@ impl ServerConfig {
     }
 }
 
+/// Worker limits must allow at least one worker, or no task would ever start.
+fn parse_worker_limit(key: &str, value: &str) -> anyhow::Result<usize> {
+    match value.parse()? {
+        0 => anyhow::bail!("{key} must be at least 1"),
+        limit => Ok(limit),
+    }
+}
+
 fn read_env(key: &str) -> Option<String> {
     env::var(key)
         .ok()
@@ -78,4 +152,29 @@ mod tests {
         assert_eq!(config.http_port, 4110);
         assert_eq!(config.ws_port, 4111);
     }
+
+    #[test]
+    fn worker_limits_are_read_from_env() {
+        let _guard = ENV_MUTEX.lock().unwrap();
+        env::set_var("AGENT_HUB_MAX_WORKERS", "8");
+        env::set_var("AGENT_HUB_MAX_WORKERS_PER_SESSION", "3");
+
+        let config = ServerConfig::from_env().expect("config loads worker limits");
+        env::remove_var("AGENT_HUB_MAX_WORKERS");
+        env::remove_var("AGENT_HUB_MAX_WORKERS_PER_SESSION");
+
+        assert_eq!(config.max_workers, 8);
+        assert_eq!(config.max_workers_per_session, 3);
+    }
+
+    #[test]
+    fn zero_worker_limits_are_rejected() {
+        let _guard = ENV_MUTEX.lock().unwrap();
+        env::set_var("AGENT_HUB_MAX_WORKERS_PER_SESSION", "0");
+        let result = ServerConfig::from_env();
+        env::remove_var("AGENT_HUB_MAX_WORKERS_PER_SESSION");
+
+        let err = result.err().expect("zero workers per session is rejected");
+        assert!(err.to_string().contains("AGENT_HUB_MAX_WORKERS_PER_SESSION"));
+    }
 }
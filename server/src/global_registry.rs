@@ -101,7 +101,7 @@ pub fn upsert_project(registry: &mut GlobalProjectRegistry, summary: ProjectSumm
 
 fn write_registry(path: &Path, registry: &GlobalProjectRegistry) -> io::Result<()> {
     let serialized = serde_json::to_vec_pretty(registry)
-        .map_err(|err| io::Error::new(ErrorKind::Other, err.to_string()))?;
+        .map_err(|err| io::Error::other(err.to_string()))?;
     fs::write(path, serialized)?;
     Ok(())
 }
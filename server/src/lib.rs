@@ -4,10 +4,15 @@ pub mod global_registry;
 pub mod llm;
 pub mod profiles;
 pub mod project_sessions;
+pub mod prompts;
+pub mod runtime_state;
+pub mod session_persistence;
 pub mod sessions;
 pub mod state;
 pub mod utils;
 pub mod vibe_project;
 pub mod agents;
 pub mod tasks;
+pub mod template;
+pub mod usage;
 pub mod ws;
@@ -7,7 +7,7 @@ impl ProviderAdapter for ClaudeAdapter {
         "claude".to_string()
     }
 
-    fn get_args(&self, prompt_file: &str, model: &str) -> Vec<String> {
+    fn get_args(&self, prompt_file: &str, _model: &str) -> Vec<String> {
         // Claude Code CLI (claude) usually takes the prompt as a positional argument
         // or reads from stdin.
         // For non-interactive, we use -p/--print.
@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+
 use super::ProviderAdapter;
 
-pub struct CodexAdapter;
+#[derive(Default)]
+pub struct CodexAdapter {
+    pub base_url: Option<String>, // Endpoint of an OpenAI-compatible server instead of OpenAI
+}
 
 impl ProviderAdapter for CodexAdapter {
     fn get_command(&self) -> String {
@@ -18,4 +23,12 @@ impl ProviderAdapter for CodexAdapter {
             format!("Please read {} and follow the instructions.", prompt_file),
         ]
     }
+
+    fn get_env(&self) -> HashMap<String, String> {
+        // Codex reads the endpoint of its built-in OpenAI provider from here
+        self.base_url
+            .iter()
+            .map(|base_url| ("OPENAI_BASE_URL".to_string(), base_url.clone()))
+            .collect()
+    }
 }
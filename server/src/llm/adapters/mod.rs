@@ -2,18 +2,48 @@ pub mod gemini;
 pub mod claude;
 pub mod codex;
 
+use std::collections::HashMap;
+
 use crate::llm::ProviderKind;
 
 pub trait ProviderAdapter: Send + Sync {
     fn get_command(&self) -> String;
     fn get_args(&self, prompt_file: &str, model: &str) -> Vec<String>;
+
+    /// Environment the CLI needs on top of the agent's own, e.g. the endpoint
+    /// of a self-hosted provider.
+    fn get_env(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
 }
 
 pub fn get_adapter(kind: &ProviderKind) -> Box<dyn ProviderAdapter> {
     match kind {
         ProviderKind::Gemini => Box::new(gemini::GeminiAdapter),
         ProviderKind::Claude => Box::new(claude::ClaudeAdapter),
-        ProviderKind::Codex => Box::new(codex::CodexAdapter),
+        ProviderKind::Codex => Box::new(codex::CodexAdapter::default()),
+        // Codex CLI speaks the same protocol and can be pointed at the endpoint
+        ProviderKind::OpenAiCompatible { base_url } => Box::new(codex::CodexAdapter {
+            base_url: Some(base_url.clone()),
+        }),
         ProviderKind::Dummy => Box::new(gemini::GeminiAdapter), // Fallback to Gemini for now
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_compatible_workers_use_the_base_url() {
+        let kind = ProviderKind::OpenAiCompatible {
+            base_url: "http://localhost:11434/v1".to_string(),
+        };
+        let env = get_adapter(&kind).get_env();
+        assert_eq!(
+            env.get("OPENAI_BASE_URL").map(String::as_str),
+            Some("http://localhost:11434/v1")
+        );
+        assert!(get_adapter(&ProviderKind::Codex).get_env().is_empty());
+    }
+}
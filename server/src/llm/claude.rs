@@ -0,0 +1,273 @@
+use std::env;
+
+use async_stream::try_stream;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{sse, LlmChunk, LlmClient, LlmError, LlmRequest, LlmStream, MessageRole};
+use crate::usage::TokenUsage;
+
+pub const DEFAULT_ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Streams chat completions from the Anthropic Messages API.
+pub struct ClaudeClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl ClaudeClient {
+    pub fn new(base_url: impl Into<String>, api_key: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    /// Builds a client from `ANTHROPIC_API_KEY` and `ANTHROPIC_BASE_URL`. Returns
+    /// `None` when neither is set, leaving the provider unconfigured.
+    pub fn from_env() -> Option<Self> {
+        let api_key = env::var("ANTHROPIC_API_KEY").ok();
+        let base_url = env::var("ANTHROPIC_BASE_URL").ok();
+        if api_key.is_none() && base_url.is_none() {
+            return None;
+        }
+        Some(Self::new(
+            base_url.unwrap_or_else(|| DEFAULT_ANTHROPIC_BASE_URL.to_string()),
+            api_key,
+        ))
+    }
+}
+
+impl LlmClient for ClaudeClient {
+    fn stream(&self, request: &LlmRequest) -> LlmStream {
+        let url = format!("{}/v1/messages", self.base_url);
+        let mut builder = self
+            .http
+            .post(url)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&request_body(request));
+        if let Some(key) = &self.api_key {
+            builder = builder.header("x-api-key", key);
+        }
+
+        Box::pin(try_stream! {
+            let response = builder
+                .send()
+                .await
+                .map_err(|err| LlmError::RequestFailed(err.to_string()))?;
+            if !response.status().is_success() {
+                Err(sse::error_from_response(response).await)?;
+            } else {
+                let mut events = Box::pin(sse::data_events(response));
+                let mut usage = TokenUsage::default();
+                while let Some(data) = events.next().await {
+                    let event: StreamEvent = serde_json::from_str(&data?)
+                        .map_err(|err| LlmError::StreamFailure(err.to_string()))?;
+                    match event {
+                        StreamEvent::MessageStart { message } => {
+                            if let Some(start) = message.usage {
+                                usage.prompt_tokens = start.input_tokens;
+                                usage.completion_tokens = start.output_tokens;
+                            }
+                        }
+                        StreamEvent::MessageDelta { usage: Some(delta) } => {
+                            // output_tokens in message_delta is the running total
+                            usage.completion_tokens = delta.output_tokens;
+                        }
+                        StreamEvent::ContentBlockDelta { delta: Delta::TextDelta { text } } => {
+                            yield LlmChunk::Text(text);
+                        }
+                        StreamEvent::Error { error } => {
+                            Err(LlmError::StreamFailure(format!("{}: {}", error.kind, error.message)))?;
+                        }
+                        StreamEvent::MessageStop => break,
+                        _ => {}
+                    }
+                }
+                if usage.total_tokens() > 0 {
+                    yield LlmChunk::Usage(usage);
+                }
+            }
+        })
+    }
+}
+
+fn request_body(request: &LlmRequest) -> Value {
+    // The Messages API takes system prompts as a top-level field, not as a turn.
+    let system: Vec<&str> = request
+        .messages
+        .iter()
+        .filter(|message| matches!(message.role, MessageRole::System))
+        .map(|message| message.content.as_str())
+        .collect();
+    let messages: Vec<Value> = request
+        .messages
+        .iter()
+        .filter_map(|message| {
+            let role = match message.role {
+                MessageRole::User => "user",
+                MessageRole::Assistant => "assistant",
+                MessageRole::System => return None,
+            };
+            Some(json!({ "role": role, "content": message.content }))
+        })
+        .collect();
+
+    let mut body = json!({
+        "model": request.config.model,
+        "max_tokens": DEFAULT_MAX_TOKENS,
+        "temperature": request.config.temperature,
+        "stream": true,
+        "messages": messages,
+    });
+    if !system.is_empty() {
+        body["system"] = Value::String(system.join("\n\n"));
+    }
+    body
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart { message: MessageStartBody },
+    MessageDelta { usage: Option<UsageCounts> },
+    ContentBlockDelta { delta: Delta },
+    MessageStop,
+    Error { error: ApiError },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Delta {
+    TextDelta {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct MessageStartBody {
+    usage: Option<UsageCounts>,
+}
+
+#[derive(Deserialize)]
+struct UsageCounts {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{test_support::serve, LlmConfig, LlmMessage, ProviderKind};
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    fn request(messages: Vec<LlmMessage>) -> LlmRequest {
+        LlmRequest {
+            config: LlmConfig {
+                provider: ProviderKind::Claude,
+                model: "claude-test".into(),
+                temperature: 0.3,
+            },
+            messages,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streams_text_deltas() {
+        let captured = Arc::new(Mutex::new(None));
+        let captured_clone = captured.clone();
+        let app = Router::new().route(
+            "/v1/messages",
+            post(move |headers: HeaderMap, Json(body): Json<Value>| {
+                let captured = captured_clone.clone();
+                async move {
+                    let key = headers.get("x-api-key").map(|v| v.to_str().unwrap().to_string());
+                    *captured.lock().unwrap() = Some((key, body));
+                    (
+                        [("content-type", "text/event-stream")],
+                        "event: message_start\n\
+                         data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n\
+                         event: content_block_delta\n\
+                         data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n\
+                         event: ping\n\
+                         data: {\"type\":\"ping\"}\n\n\
+                         event: content_block_delta\n\
+                         data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" there\"}}\n\n\
+                         event: message_delta\n\
+                         data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":4}}\n\n\
+                         event: message_stop\n\
+                         data: {\"type\":\"message_stop\"}\n\n",
+                    )
+                }
+            }),
+        );
+        let base_url = serve(app).await;
+
+        let client = ClaudeClient::new(base_url, Some("secret".to_string()));
+        let stream = client.stream(&request(vec![
+            LlmMessage { role: MessageRole::System, content: "Be brief".into() },
+            LlmMessage { role: MessageRole::User, content: "Hello".into() },
+        ]));
+        let chunks: Vec<LlmChunk> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(
+            chunks,
+            vec![
+                LlmChunk::Text("Hi".into()),
+                LlmChunk::Text(" there".into()),
+                LlmChunk::Usage(TokenUsage::new(12, 4)),
+            ]
+        );
+
+        let (key, body) = captured.lock().unwrap().take().expect("request was captured");
+        assert_eq!(key.as_deref(), Some("secret"));
+        assert_eq!(body["system"], "Be brief");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["model"], "claude-test");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn test_error_event_fails_stream() {
+        let app = Router::new().route(
+            "/v1/messages",
+            post(|| async {
+                (
+                    [("content-type", "text/event-stream")],
+                    "event: error\n\
+                     data: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\n",
+                )
+            }),
+        );
+        let base_url = serve(app).await;
+
+        let client = ClaudeClient::new(base_url, None);
+        let mut stream = client.stream(&request(vec![LlmMessage {
+            role: MessageRole::User,
+            content: "Hello".into(),
+        }]));
+        match stream.next().await {
+            Some(Err(LlmError::StreamFailure(message))) => assert!(message.contains("overloaded_error")),
+            _ => panic!("expected stream failure"),
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use futures::StreamExt;
 use tokio_stream::iter;
 
-use super::{LlmClient, LlmMessage, LlmRequest, LlmStream, MessageRole};
+use super::{LlmChunk, LlmClient, LlmMessage, LlmRequest, LlmStream, MessageRole};
+use crate::usage::TokenUsage;
 
+#[derive(Default)]
 pub struct DummyClient;
 
 impl DummyClient {
@@ -18,11 +20,19 @@ impl LlmClient for DummyClient {
             .map(|msg| format!("Echo: {}", msg.content))
             .unwrap_or(fallback);
 
-        let chunks = text
+        let mut chunks = text
             .split_whitespace()
-            .map(|token| format!("{token} "))
+            .map(|token| LlmChunk::Text(format!("{token} ")))
             .collect::<Vec<_>>();
 
+        // One "token" per whitespace-separated word keeps usage accounting testable offline
+        let prompt_tokens = request
+            .messages
+            .iter()
+            .map(|message| message.content.split_whitespace().count() as u64)
+            .sum();
+        chunks.push(LlmChunk::Usage(TokenUsage::new(prompt_tokens, chunks.len() as u64)));
+
         Box::pin(iter(chunks).map(Ok))
     }
 }
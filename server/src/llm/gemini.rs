@@ -0,0 +1,249 @@
+use std::env;
+
+use async_stream::try_stream;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{sse, LlmChunk, LlmClient, LlmError, LlmMessage, LlmRequest, LlmStream, MessageRole};
+use crate::usage::TokenUsage;
+
+pub const DEFAULT_GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
+/// Streams chat completions from Gemini's `streamGenerateContent` endpoint.
+pub struct GeminiClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl GeminiClient {
+    pub fn new(base_url: impl Into<String>, api_key: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    /// Builds a client from `GEMINI_API_KEY` and `GEMINI_BASE_URL`. Returns `None`
+    /// when neither is set, leaving the provider unconfigured.
+    pub fn from_env() -> Option<Self> {
+        let api_key = env::var("GEMINI_API_KEY").ok();
+        let base_url = env::var("GEMINI_BASE_URL").ok();
+        if api_key.is_none() && base_url.is_none() {
+            return None;
+        }
+        Some(Self::new(
+            base_url.unwrap_or_else(|| DEFAULT_GEMINI_BASE_URL.to_string()),
+            api_key,
+        ))
+    }
+}
+
+impl LlmClient for GeminiClient {
+    fn stream(&self, request: &LlmRequest) -> LlmStream {
+        let url = format!(
+            "{}/v1beta/models/{}:streamGenerateContent?alt=sse",
+            self.base_url, request.config.model
+        );
+        let body = request_body(request);
+        let mut builder = self.http.post(url).json(&body);
+        if let Some(key) = &self.api_key {
+            builder = builder.header("x-goog-api-key", key);
+        }
+
+        Box::pin(try_stream! {
+            let response = builder
+                .send()
+                .await
+                .map_err(|err| LlmError::RequestFailed(err.to_string()))?;
+            if !response.status().is_success() {
+                Err(sse::error_from_response(response).await)?;
+            } else {
+                let mut events = Box::pin(sse::data_events(response));
+                // usageMetadata is cumulative, so only the last one counts
+                let mut usage = None;
+                while let Some(data) = events.next().await {
+                    let chunk: StreamChunk = serde_json::from_str(&data?)
+                        .map_err(|err| LlmError::StreamFailure(err.to_string()))?;
+                    let text = chunk.text();
+                    if !text.is_empty() {
+                        yield LlmChunk::Text(text);
+                    }
+                    if let Some(metadata) = chunk.usage_metadata {
+                        usage = Some(TokenUsage::new(
+                            metadata.prompt_token_count,
+                            metadata.candidates_token_count,
+                        ));
+                    }
+                }
+                if let Some(usage) = usage {
+                    yield LlmChunk::Usage(usage);
+                }
+            }
+        })
+    }
+}
+
+fn request_body(request: &LlmRequest) -> Value {
+    let system: Vec<&str> = request
+        .messages
+        .iter()
+        .filter(|message| matches!(message.role, MessageRole::System))
+        .map(|message| message.content.as_str())
+        .collect();
+    let contents: Vec<Value> = request
+        .messages
+        .iter()
+        .filter_map(|message| {
+            let role = match message.role {
+                MessageRole::User => "user",
+                MessageRole::Assistant => "model",
+                MessageRole::System => return None,
+            };
+            Some(content(role, message))
+        })
+        .collect();
+
+    let mut body = json!({
+        "contents": contents,
+        "generationConfig": { "temperature": request.config.temperature },
+    });
+    if !system.is_empty() {
+        body["systemInstruction"] = json!({ "parts": [{ "text": system.join("\n\n") }] });
+    }
+    body
+}
+
+fn content(role: &str, message: &LlmMessage) -> Value {
+    json!({ "role": role, "parts": [{ "text": message.content }] })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamChunk {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+}
+
+#[derive(Deserialize)]
+struct Candidate {
+    content: Option<CandidateContent>,
+}
+
+#[derive(Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Deserialize)]
+struct Part {
+    text: Option<String>,
+}
+
+impl StreamChunk {
+    fn text(&self) -> String {
+        self.candidates
+            .iter()
+            .filter_map(|candidate| candidate.content.as_ref())
+            .flat_map(|content| content.parts.iter())
+            .filter_map(|part| part.text.as_deref())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{test_support::serve, LlmConfig, ProviderKind};
+    use axum::{extract::Path, http::HeaderMap, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    fn request(messages: Vec<LlmMessage>) -> LlmRequest {
+        LlmRequest {
+            config: LlmConfig {
+                provider: ProviderKind::Gemini,
+                model: "gemini-test".into(),
+                temperature: 0.7,
+            },
+            messages,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streams_text_from_sse() {
+        let captured = Arc::new(Mutex::new(None));
+        let captured_clone = captured.clone();
+        let app = Router::new().route(
+            "/v1beta/models/:model",
+            post(move |Path(model): Path<String>, headers: HeaderMap, Json(body): Json<Value>| {
+                let captured = captured_clone.clone();
+                async move {
+                    let key = headers.get("x-goog-api-key").map(|v| v.to_str().unwrap().to_string());
+                    *captured.lock().unwrap() = Some((model, key, body));
+                    (
+                        [("content-type", "text/event-stream")],
+                        "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hello\"}]}}]}\r\n\r\n\
+                         data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\" world\"}]}}],\"usageMetadata\":{\"promptTokenCount\":5,\"candidatesTokenCount\":2}}\r\n\r\n",
+                    )
+                }
+            }),
+        );
+        let base_url = serve(app).await;
+
+        let client = GeminiClient::new(base_url, Some("secret".to_string()));
+        let stream = client.stream(&request(vec![
+            LlmMessage { role: MessageRole::System, content: "Be brief".into() },
+            LlmMessage { role: MessageRole::User, content: "Hi".into() },
+            LlmMessage { role: MessageRole::Assistant, content: "Hey".into() },
+        ]));
+        let chunks: Vec<LlmChunk> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(
+            chunks,
+            vec![
+                LlmChunk::Text("Hello".into()),
+                LlmChunk::Text(" world".into()),
+                LlmChunk::Usage(TokenUsage::new(5, 2)),
+            ]
+        );
+
+        let (model, key, body) = captured.lock().unwrap().take().expect("request was captured");
+        assert_eq!(model, "gemini-test:streamGenerateContent");
+        assert_eq!(key.as_deref(), Some("secret"));
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief");
+        assert_eq!(body["contents"][0]["role"], "user");
+        assert_eq!(body["contents"][1]["role"], "model");
+        assert!((body["generationConfig"]["temperature"].as_f64().unwrap() - 0.7).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_http_error_is_reported() {
+        let app = Router::new().route(
+            "/v1beta/models/:model",
+            post(|| async { (axum::http::StatusCode::UNAUTHORIZED, "bad key") }),
+        );
+        let base_url = serve(app).await;
+
+        let client = GeminiClient::new(base_url, None);
+        let mut stream = client.stream(&request(vec![LlmMessage {
+            role: MessageRole::User,
+            content: "Hi".into(),
+        }]));
+        match stream.next().await {
+            Some(Err(LlmError::RequestFailed(message))) => assert!(message.contains("401")),
+            _ => panic!("expected request failure"),
+        }
+    }
+}
@@ -1,13 +1,29 @@
-use std::{pin::Pin, str::FromStr, sync::Arc};
+use std::{env, pin::Pin, str::FromStr, sync::Arc};
 
 use futures::Stream;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::usage::TokenUsage;
+
 pub mod dummy;
 pub mod adapters;
+pub mod claude;
+pub mod gemini;
+pub mod openai;
+mod sse;
+
+pub type LlmStream = Pin<Box<dyn Stream<Item = Result<LlmChunk, LlmError>> + Send>>;
+
+/// One item of a streamed completion.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LlmChunk {
+    Text(String),
+    /// Token usage for the whole request; sent last, when the provider reports it.
+    Usage(TokenUsage),
+}
 
-pub type LlmStream = Pin<Box<dyn Stream<Item = Result<String, LlmError>> + Send>>;
+pub const DEFAULT_OPENAI_COMPATIBLE_BASE_URL: &str = "http://localhost:8080/v1";
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -16,6 +32,21 @@ pub enum ProviderKind {
     Gemini,
     Claude,
     Codex,
+    /// Any server speaking the OpenAI chat completions protocol (llama.cpp, Ollama, vLLM, ...).
+    #[serde(rename = "openai-compatible", rename_all = "camelCase")]
+    OpenAiCompatible { base_url: String },
+}
+
+impl ProviderKind {
+    /// Overrides the endpoint of an `OpenAiCompatible` provider; other providers are unchanged.
+    pub fn with_base_url(self, base_url: Option<String>) -> Self {
+        match (self, base_url) {
+            (ProviderKind::OpenAiCompatible { .. }, Some(base_url)) => {
+                ProviderKind::OpenAiCompatible { base_url }
+            }
+            (kind, _) => kind,
+        }
+    }
 }
 
 impl FromStr for ProviderKind {
@@ -27,6 +58,9 @@ impl FromStr for ProviderKind {
             "claude" => Ok(ProviderKind::Claude),
             "codex" => Ok(ProviderKind::Codex),
             "dummy" => Ok(ProviderKind::Dummy),
+            "openai-compatible" | "local" => Ok(ProviderKind::OpenAiCompatible {
+                base_url: DEFAULT_OPENAI_COMPATIBLE_BASE_URL.to_string(),
+            }),
             other => Err(LlmError::UnknownProvider(other.to_string())),
         }
     }
@@ -77,24 +111,90 @@ pub enum LlmError {
     UnknownProvider(String),
     #[error("stream failure: {0}")]
     StreamFailure(String),
+    #[error("request failed: {0}")]
+    RequestFailed(String),
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct LlmRegistry {
     dummy: Arc<dummy::DummyClient>,
+    gemini: Option<Arc<gemini::GeminiClient>>,
+    claude: Option<Arc<claude::ClaudeClient>>,
+    codex: Option<Arc<openai::OpenAiClient>>,
+    openai_compatible_key: Option<String>,
 }
 
 impl LlmRegistry {
     pub fn new() -> Self {
         Self {
             dummy: Arc::new(dummy::DummyClient::new()),
+            gemini: gemini::GeminiClient::from_env().map(Arc::new),
+            claude: claude::ClaudeClient::from_env().map(Arc::new),
+            codex: openai::OpenAiClient::from_env().map(Arc::new),
+            openai_compatible_key: env::var("AGENT_HUB_OPENAI_COMPATIBLE_API_KEY").ok(),
         }
     }
 
     pub fn stream(&self, request: LlmRequest) -> Result<LlmStream, LlmError> {
-        match request.config.provider {
+        match &request.config.provider {
             ProviderKind::Dummy => Ok(self.dummy.stream(&request)),
-            ProviderKind::Gemini | ProviderKind::Claude | ProviderKind::Codex => Err(LlmError::ProviderNotConfigured),
+            ProviderKind::Gemini => self
+                .gemini
+                .as_ref()
+                .map(|client| client.stream(&request))
+                .ok_or(LlmError::ProviderNotConfigured),
+            ProviderKind::Claude => self
+                .claude
+                .as_ref()
+                .map(|client| client.stream(&request))
+                .ok_or(LlmError::ProviderNotConfigured),
+            ProviderKind::Codex => self
+                .codex
+                .as_ref()
+                .map(|client| client.stream(&request))
+                .ok_or(LlmError::ProviderNotConfigured),
+            ProviderKind::OpenAiCompatible { base_url } => Ok(openai::OpenAiClient::new(
+                base_url.clone(),
+                self.openai_compatible_key.clone(),
+            )
+            .stream(&request)),
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use axum::Router;
+
+    /// Serves `app` on an ephemeral local port and returns its base URL.
+    pub async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openai_compatible_provider_round_trips() {
+        let kind = ProviderKind::from_str("openai-compatible")
+            .unwrap()
+            .with_base_url(Some("http://localhost:11434/v1".to_string()));
+        let json = serde_json::to_value(&kind).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "openai-compatible": { "baseUrl": "http://localhost:11434/v1" } })
+        );
+        assert_eq!(serde_json::from_value::<ProviderKind>(json).unwrap(), kind);
+        assert_eq!(
+            serde_json::to_value(ProviderKind::Codex).unwrap(),
+            serde_json::json!("codex")
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::env;
+
+use async_stream::try_stream;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{sse, LlmChunk, LlmClient, LlmError, LlmRequest, LlmStream, MessageRole};
+use crate::usage::TokenUsage;
+
+pub const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Streams chat completions from any server speaking the OpenAI
+/// `/chat/completions` protocol. `base_url` includes the version prefix,
+/// e.g. `http://localhost:11434/v1` for Ollama.
+pub struct OpenAiClient {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiClient {
+    pub fn new(base_url: impl Into<String>, api_key: Option<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    /// Builds the Codex client from `OPENAI_API_KEY` and `OPENAI_BASE_URL`.
+    /// Returns `None` when neither is set, leaving the provider unconfigured.
+    pub fn from_env() -> Option<Self> {
+        let api_key = env::var("OPENAI_API_KEY").ok();
+        let base_url = env::var("OPENAI_BASE_URL").ok();
+        if api_key.is_none() && base_url.is_none() {
+            return None;
+        }
+        Some(Self::new(
+            base_url.unwrap_or_else(|| DEFAULT_OPENAI_BASE_URL.to_string()),
+            api_key,
+        ))
+    }
+}
+
+impl LlmClient for OpenAiClient {
+    fn stream(&self, request: &LlmRequest) -> LlmStream {
+        let url = format!("{}/chat/completions", self.base_url);
+        let mut builder = self.http.post(url).json(&request_body(request));
+        if let Some(key) = &self.api_key {
+            builder = builder.bearer_auth(key);
+        }
+
+        Box::pin(try_stream! {
+            let response = builder
+                .send()
+                .await
+                .map_err(|err| LlmError::RequestFailed(err.to_string()))?;
+            if !response.status().is_success() {
+                Err(sse::error_from_response(response).await)?;
+            } else {
+                let mut events = Box::pin(sse::data_events(response));
+                while let Some(data) = events.next().await {
+                    let data = data?;
+                    if data.trim() == "[DONE]" {
+                        break;
+                    }
+                    let chunk: ChatCompletionChunk = serde_json::from_str(&data)
+                        .map_err(|err| LlmError::StreamFailure(err.to_string()))?;
+                    let text: String = chunk
+                        .choices
+                        .into_iter()
+                        .filter_map(|choice| choice.delta.content)
+                        .collect();
+                    if !text.is_empty() {
+                        yield LlmChunk::Text(text);
+                    }
+                    // Sent on a final chunk with no choices when include_usage is set
+                    if let Some(usage) = chunk.usage {
+                        yield LlmChunk::Usage(TokenUsage::new(usage.prompt_tokens, usage.completion_tokens));
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn request_body(request: &LlmRequest) -> Value {
+    let messages: Vec<Value> = request
+        .messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                MessageRole::System => "system",
+                MessageRole::User => "user",
+                MessageRole::Assistant => "assistant",
+            };
+            json!({ "role": role, "content": message.content })
+        })
+        .collect();
+
+    json!({
+        "model": request.config.model,
+        "messages": messages,
+        "temperature": request.config.temperature,
+        "stream": true,
+        "stream_options": { "include_usage": true },
+    })
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<Choice>,
+    usage: Option<ChunkUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChunkUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    delta: ChoiceDelta,
+}
+
+#[derive(Deserialize)]
+struct ChoiceDelta {
+    content: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{test_support::serve, LlmConfig, LlmMessage, ProviderKind};
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_streams_chat_completion_deltas() {
+        let captured = Arc::new(Mutex::new(None));
+        let captured_clone = captured.clone();
+        let app = Router::new().route(
+            "/v1/chat/completions",
+            post(move |headers: HeaderMap, Json(body): Json<Value>| {
+                let captured = captured_clone.clone();
+                async move {
+                    let auth = headers.get("authorization").map(|v| v.to_str().unwrap().to_string());
+                    *captured.lock().unwrap() = Some((auth, body));
+                    (
+                        [("content-type", "text/event-stream")],
+                        "data: {\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+                         data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Local\"}}]}\n\n\
+                         data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\" model\"}}]}\n\n\
+                         data: {\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2,\"total_tokens\":11}}\n\n\
+                         data: [DONE]\n\n",
+                    )
+                }
+            }),
+        );
+        let base_url = serve(app).await;
+
+        let client = OpenAiClient::new(format!("{}/v1/", base_url), Some("sk-test".to_string()));
+        let stream = client.stream(&LlmRequest {
+            config: LlmConfig {
+                provider: ProviderKind::OpenAiCompatible { base_url: base_url.clone() },
+                model: "llama3".into(),
+                temperature: 0.5,
+            },
+            messages: vec![
+                LlmMessage { role: MessageRole::System, content: "Be brief".into() },
+                LlmMessage { role: MessageRole::User, content: "Hi".into() },
+            ],
+        });
+        let chunks: Vec<LlmChunk> = stream.map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(
+            chunks,
+            vec![
+                LlmChunk::Text("Local".into()),
+                LlmChunk::Text(" model".into()),
+                LlmChunk::Usage(TokenUsage::new(9, 2)),
+            ]
+        );
+
+        let (auth, body) = captured.lock().unwrap().take().expect("request was captured");
+        assert_eq!(auth.as_deref(), Some("Bearer sk-test"));
+        assert_eq!(body["model"], "llama3");
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "Hi");
+    }
+}
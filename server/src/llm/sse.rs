@@ -0,0 +1,106 @@
+use async_stream::try_stream;
+use futures::{Stream, StreamExt};
+
+use super::LlmError;
+
+/// Turns a `text/event-stream` response into the `data:` payload of each event.
+/// Multi-line data fields are joined with `\n`, per the SSE spec.
+pub fn data_events(response: reqwest::Response) -> impl Stream<Item = Result<String, LlmError>> {
+    try_stream! {
+        let mut body = response.bytes_stream();
+        let mut buffer = EventBuffer::default();
+
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|err| LlmError::StreamFailure(err.to_string()))?;
+            buffer.push(&chunk);
+            while let Some(event) = buffer.next_event() {
+                if let Some(data) = event_data(&event) {
+                    yield data;
+                }
+            }
+        }
+
+        if let Some(data) = event_data(&buffer.rest()) {
+            yield data;
+        }
+    }
+}
+
+/// Raw bytes of an event stream. They are only decoded once an event is
+/// complete, so a character split across network chunks stays intact.
+#[derive(Default)]
+struct EventBuffer {
+    bytes: Vec<u8>,
+}
+
+impl EventBuffer {
+    fn push(&mut self, chunk: &[u8]) {
+        self.bytes.extend_from_slice(chunk);
+        // Normalise CRLF so event boundaries are always a blank line. A trailing
+        // `\r` is kept until the next chunk shows whether `\n` follows.
+        if self.bytes.contains(&b'\r') {
+            let mut normalized = Vec::with_capacity(self.bytes.len());
+            for (index, byte) in self.bytes.iter().enumerate() {
+                if *byte == b'\r' && self.bytes.get(index + 1) == Some(&b'\n') {
+                    continue;
+                }
+                normalized.push(*byte);
+            }
+            self.bytes = normalized;
+        }
+    }
+
+    /// Removes and returns the next event terminated by a blank line.
+    fn next_event(&mut self) -> Option<String> {
+        let end = self.bytes.windows(2).position(|pair| pair == b"\n\n")?;
+        let event: Vec<u8> = self.bytes.drain(..end + 2).collect();
+        Some(String::from_utf8_lossy(&event).into_owned())
+    }
+
+    /// Whatever followed the last complete event.
+    fn rest(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+}
+
+fn event_data(event: &str) -> Option<String> {
+    let lines: Vec<&str> = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// Reads a non-success response into an `LlmError` carrying the status and body.
+pub async fn error_from_response(response: reqwest::Response) -> LlmError {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    LlmError::RequestFailed(format!("HTTP {status}: {body}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_characters_split_across_chunks_survive() {
+        let mut buffer = EventBuffer::default();
+        let body = "data: h\u{e9}llo \u{1f600}\r\n\r\ndata: next".as_bytes();
+        // Split inside `é`, inside the emoji and between `\r` and `\n`
+        for chunk in [&body[..8], &body[8..15], &body[15..18]] {
+            buffer.push(chunk);
+            assert!(buffer.next_event().is_none());
+        }
+        buffer.push(&body[18..]);
+
+        let event = buffer.next_event().unwrap();
+        assert_eq!(event_data(&event).as_deref(), Some("h\u{e9}llo \u{1f600}"));
+        assert!(buffer.next_event().is_none());
+        assert_eq!(event_data(&buffer.rest()).as_deref(), Some("next"));
+    }
+}
@@ -1,6 +1,5 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 use std::env;
-use std::path::PathBuf;
 use anyhow::Context;
 
 use agent_hub_server::{
@@ -9,8 +8,10 @@ use agent_hub_server::{
     config::ServerConfig,
     global_registry::{load_or_init_registry, GlobalProjectRegistry, RegistryError},
     llm::LlmRegistry,
-    profiles::ProfileCatalog,
+    profiles::{watch_profiles, ProfileCatalog},
     project_sessions::ProjectSession,
+    runtime_state,
+    session_persistence::JsonlSessionLog,
     sessions::SessionStore,
     state::AppState,
     ws,
@@ -20,9 +21,12 @@ use agent_hub_server::{
 };
 use axum::Router;
 use parking_lot::RwLock;
-use tracing::error;
+use tracing::{error, warn};
 use tracing_subscriber::{fmt, EnvFilter};
 
+const RUNTIME_SNAPSHOT_INTERVAL_SECS: u64 = 5;
+const AGENT_WATCHDOG_INTERVAL_SECS: u64 = 10;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv::dotenv().ok(); // Load .env file if it exists
@@ -32,7 +36,10 @@ async fn main() -> anyhow::Result<()> {
 
     let config = ServerConfig::from_env()?;
     let profiles = Arc::new(ProfileCatalog::load(&config.prompt_profile_dir)?);
-    let sessions = Arc::new(SessionStore::new());
+    let sessions = Arc::new(
+        SessionStore::with_persistence(Arc::new(JsonlSessionLog::open_default()?))
+            .context("Failed to replay persisted sessions")?,
+    );
     let llms = Arc::new(LlmRegistry::new());
     let registry = match load_or_init_registry() {
         Ok(registry) => registry,
@@ -52,9 +59,17 @@ async fn main() -> anyhow::Result<()> {
     
     let task_dispatcher = TaskDispatcher::new(
         agent_spawner.clone(), 
+        agents.clone(),
         Arc::new(config.clone()), 
         server_root_dir.clone(), // New argument
-    );
+    )
+    .with_session_events(sessions.clone())
+    .with_project_sessions(project_sessions.clone())
+    .with_profiles(profiles.clone());
+    sessions.forward_agent_events(agents.events());
+    if let Err(e) = watch_profiles(profiles.clone(), sessions.clone()) {
+        warn!("Prompt profiles will not be reloaded on change: {:#}", e);
+    }
 
     let state = AppState {
         config: config.clone(),
@@ -65,9 +80,43 @@ async fn main() -> anyhow::Result<()> {
         project_sessions,
         agents: agents.clone(),
         agent_spawner,
+        dispatcher: task_dispatcher.clone(),
         server_root_dir: server_root_dir.clone(),
     };
 
+    // Reload sessions, agents and task queues saved before the last shutdown
+    runtime_state::restore_projects(&state, &task_dispatcher);
+    task_dispatcher.resume().await;
+    task_dispatcher.watch_agent_events();
+
+    let snapshot_state = state.clone();
+    let snapshot_dispatcher = task_dispatcher.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(RUNTIME_SNAPSHOT_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let (state, dispatcher) = (snapshot_state.clone(), snapshot_dispatcher.clone());
+            if let Err(err) = tokio::task::spawn_blocking(move || {
+                runtime_state::snapshot_projects(&state, &dispatcher)
+            })
+            .await
+            {
+                error!("Runtime snapshot task failed: {err}");
+            }
+        }
+    });
+
+    let watchdog_dispatcher = task_dispatcher.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(AGENT_WATCHDOG_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            watchdog_dispatcher.enforce_timeouts().await;
+            // Picks up retries whose backoff has elapsed
+            watchdog_dispatcher.resume().await;
+        }
+    });
+
     // Initialize and spawn ResultWatcher
     let result_watcher = ResultWatcher::new(
         state.agents.clone(),
@@ -1,10 +1,34 @@
-use std::{collections::HashMap, fs, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
+use anyhow::Context;
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::RwLock;
 use serde::Serialize;
+use tracing::{error, info, warn};
 
-#[derive(Clone)]
+use crate::sessions::{SessionStore, WsEvent};
+use crate::template::Template;
+
+/// How long the watcher waits for an editor's burst of writes to settle.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Prompt profiles under one directory. The catalog can be reloaded in place,
+/// so holders of an `Arc<ProfileCatalog>` see edits without restarting.
 pub struct ProfileCatalog {
+    dir: PathBuf,
+    loaded: RwLock<LoadedProfiles>,
+}
+
+#[derive(Default)]
+struct LoadedProfiles {
     profiles: HashMap<String, PromptProfile>,
+    invalid: Vec<InvalidProfile>,
 }
 
 #[derive(Clone, Serialize)]
@@ -15,6 +39,15 @@ pub struct ProfileSummary {
     pub modes: Vec<String>,
 }
 
+/// A profile directory that could not be loaded. Its last version that did
+/// load, if any, is still served until the profile is fixed.
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidProfile {
+    pub id: String,
+    pub error: String,
+    pub serving_previous: bool,
+}
+
 #[derive(Clone)]
 pub struct PromptProfile {
     pub id: String,
@@ -25,58 +58,33 @@ pub struct PromptProfile {
 }
 
 impl ProfileCatalog {
+    /// Loads every profile in `dir`. Only a missing or unreadable directory is
+    /// an error; profiles that fail to load are listed by [`Self::invalid`].
     pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let mut profiles = HashMap::new();
-        let dir = dir.as_ref();
-        if !dir.exists() {
-            anyhow::bail!("Prompt profile directory {} not found", dir.display());
-        }
+        let dir = dir.as_ref().to_path_buf();
+        let loaded = load_profiles(&dir, &LoadedProfiles::default())?;
+        Ok(Self {
+            dir,
+            loaded: RwLock::new(loaded),
+        })
+    }
 
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            if !entry.file_type()?.is_dir() {
-                continue;
-            }
-            let id = entry.file_name().to_string_lossy().to_string();
-            let name = title_case(&id);
-            let agents_path = entry.path().join("AGENTS.md");
-            let agents_doc = fs::read_to_string(&agents_path)?;
-            let modes_dir = entry.path().join("MODES");
-            let mut modes = HashMap::new();
-            if modes_dir.exists() {
-                for mode_file in fs::read_dir(&modes_dir)? {
-                    let mode_file = mode_file?;
-                    if !mode_file.file_type()?.is_file() {
-                        continue;
-                    }
-                    let mode_id = mode_file
-                        .path()
-                        .file_stem()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string()
-                        .to_uppercase();
-                    let content = fs::read_to_string(mode_file.path())?;
-                    modes.insert(mode_id, content);
-                }
-            }
-            profiles.insert(
-                id.clone(),
-                PromptProfile {
-                    id,
-                    name,
-                    description: None,
-                    agents_doc,
-                    modes,
-                },
-            );
-        }
+    /// Re-reads the directory. On error the current profiles are kept, and so
+    /// is the current version of a profile whose edit does not load.
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let loaded = load_profiles(&self.dir, &self.loaded.read())?;
+        *self.loaded.write() = loaded;
+        Ok(())
+    }
 
-        Ok(Self { profiles })
+    pub fn dir(&self) -> &Path {
+        &self.dir
     }
 
     pub fn summaries(&self) -> Vec<ProfileSummary> {
-        self.profiles
+        self.loaded
+            .read()
+            .profiles
             .values()
             .map(|profile| ProfileSummary {
                 id: profile.id.clone(),
@@ -87,14 +95,138 @@ impl ProfileCatalog {
             .collect()
     }
 
+    pub fn invalid(&self) -> Vec<InvalidProfile> {
+        self.loaded.read().invalid.clone()
+    }
+
     pub fn get(&self, id: &str) -> Option<PromptProfile> {
-        self.profiles.get(id).cloned()
+        self.loaded.read().profiles.get(id).cloned()
     }
 }
 
+/// Reloads `catalog` whenever a file under its directory changes and tells
+/// every session's WebSocket subscribers with a `ProfilesChanged` event.
+pub fn watch_profiles(catalog: Arc<ProfileCatalog>, sessions: Arc<SessionStore>) -> anyhow::Result<()> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: notify::Result<notify::Event>| {
+            let _ = tx.send(res);
+        },
+        Config::default(),
+    )?;
+    watcher
+        .watch(catalog.dir(), RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch prompt profiles in {}", catalog.dir().display()))?;
+    info!("Watching prompt profiles in {:?}", catalog.dir());
+
+    tokio::spawn(async move {
+        // Dropping the watcher would stop the notifications
+        let _watcher = watcher;
+        while let Some(res) = rx.recv().await {
+            if let Err(e) = res {
+                error!("Prompt profile watch error: {:?}", e);
+                continue;
+            }
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+
+            if let Err(e) = catalog.reload() {
+                error!("Failed to reload prompt profiles: {:#}", e);
+                continue;
+            }
+            let invalid = catalog.invalid();
+            info!("Reloaded prompt profiles ({} invalid)", invalid.len());
+            sessions
+                .broadcast(WsEvent::ProfilesChanged {
+                    profiles: catalog.summaries(),
+                    invalid,
+                })
+                .await;
+        }
+    });
+    Ok(())
+}
+
+/// Loads the profiles in `dir`, falling back to their version in `previous`
+/// for those that fail to load.
+fn load_profiles(dir: &Path, previous: &LoadedProfiles) -> anyhow::Result<LoadedProfiles> {
+    if !dir.exists() {
+        anyhow::bail!("Prompt profile directory {} not found", dir.display());
+    }
+
+    let mut loaded = LoadedProfiles::default();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let id = entry.file_name().to_string_lossy().to_string();
+        match load_profile(&entry.path(), &id) {
+            Ok(profile) => {
+                loaded.profiles.insert(id, profile);
+            }
+            Err(e) => {
+                let last_good = previous.profiles.get(&id).cloned();
+                warn!("Invalid prompt profile {}: {:#}", id, e);
+                loaded.invalid.push(InvalidProfile {
+                    id: id.clone(),
+                    error: format!("{:#}", e),
+                    serving_previous: last_good.is_some(),
+                });
+                if let Some(profile) = last_good {
+                    loaded.profiles.insert(id, profile);
+                }
+            }
+        }
+    }
+    loaded.invalid.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(loaded)
+}
+
+fn load_profile(dir: &Path, id: &str) -> anyhow::Result<PromptProfile> {
+    let agents_path = dir.join("AGENTS.md");
+    let agents_doc =
+        fs::read_to_string(&agents_path).with_context(|| format!("Failed to read {}", agents_path.display()))?;
+    validate_template(&agents_path, &agents_doc)?;
+    let modes_dir = dir.join("MODES");
+    let mut modes = HashMap::new();
+    if modes_dir.exists() {
+        for mode_file in fs::read_dir(&modes_dir)? {
+            let mode_file = mode_file?;
+            if !mode_file.file_type()?.is_file() {
+                continue;
+            }
+            let mode_id = mode_file
+                .path()
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+                .to_uppercase();
+            let content = fs::read_to_string(mode_file.path())?;
+            validate_template(&mode_file.path(), &content)?;
+            modes.insert(mode_id, content);
+        }
+    }
+    Ok(PromptProfile {
+        id: id.to_string(),
+        name: title_case(id),
+        description: None,
+        agents_doc,
+        modes,
+    })
+}
+
+/// Rejects profile files whose placeholders would not render.
+fn validate_template(path: &Path, content: &str) -> anyhow::Result<()> {
+    Template::parse(content)
+        .map(|_| ())
+        .with_context(|| format!("Invalid prompt template {}", path.display()))
+}
+
 fn title_case(value: &str) -> String {
     value
-        .split(|c: char| c == '-' || c == '_' || c == ' ')
+        .split(['-', '_', ' '])
         .filter(|segment| !segment.is_empty())
         .map(|segment| {
             let mut chars = segment.chars();
@@ -106,3 +238,62 @@ fn title_case(value: &str) -> String {
         .collect::<Vec<_>>()
         .join(" ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reload_reports_invalid_profiles() {
+        let dir = tempdir().unwrap();
+        let modes = dir.path().join("default").join("MODES");
+        fs::create_dir_all(&modes).unwrap();
+        fs::write(dir.path().join("default").join("AGENTS.md"), "Agents for {{project.name}}.\n").unwrap();
+        fs::write(modes.join("worker.md"), "Work on {{task.description}}.\n").unwrap();
+        let catalog = ProfileCatalog::load(dir.path()).unwrap();
+        assert!(catalog.get("default").unwrap().modes.contains_key("WORKER"));
+
+        // An invalid edit is reported while the last good version is still served
+        fs::write(modes.join("worker.md"), "Work on {{task.title}}.\n").unwrap();
+        catalog.reload().unwrap();
+        assert_eq!(catalog.get("default").unwrap().modes["WORKER"], "Work on {{task.description}}.\n");
+        let invalid = catalog.invalid();
+        assert_eq!(invalid.len(), 1);
+        assert!(invalid[0].serving_previous);
+        assert!(invalid[0].error.contains("unknown template variable '{{task.title}}'"));
+
+        // A profile that never loaded has nothing to fall back to
+        fs::create_dir_all(dir.path().join("broken")).unwrap();
+        catalog.reload().unwrap();
+        assert!(catalog.get("broken").is_none());
+        assert!(!catalog.invalid().iter().find(|profile| profile.id == "broken").unwrap().serving_previous);
+
+        fs::write(modes.join("worker.md"), "Work on {{task.id}}.\n").unwrap();
+        catalog.reload().unwrap();
+        assert_eq!(catalog.get("default").unwrap().modes["WORKER"], "Work on {{task.id}}.\n");
+    }
+
+    #[tokio::test]
+    async fn test_watch_broadcasts_reloaded_profiles() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("default")).unwrap();
+        fs::write(dir.path().join("default").join("AGENTS.md"), "Agents.\n").unwrap();
+        let catalog = Arc::new(ProfileCatalog::load(dir.path()).unwrap());
+        let sessions = Arc::new(SessionStore::new());
+        let mut events = sessions.subscribe("s1").await.unwrap();
+        watch_profiles(catalog.clone(), sessions).unwrap();
+
+        fs::create_dir_all(dir.path().join("review")).unwrap();
+        fs::write(dir.path().join("review").join("AGENTS.md"), "Reviewers.\n").unwrap();
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap().unwrap();
+            if let WsEvent::ProfilesChanged { profiles, .. } = event {
+                if profiles.iter().any(|profile| profile.id == "review") {
+                    break;
+                }
+            }
+        }
+        assert_eq!(catalog.get("review").unwrap().agents_doc, "Reviewers.\n");
+    }
+}
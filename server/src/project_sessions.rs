@@ -1,10 +1,13 @@
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use std::collections::HashMap;
-use tracing::{info, error};
+use std::path::Path;
+use tracing::{info, error, warn};
 
+use crate::prompts::orchestrator_prompt;
 use crate::state::AppState;
+use crate::usage::TokenUsage;
+use crate::vibe_project::{load_rules, VibeRules};
 use std::env; 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +19,12 @@ pub struct ProjectSession {
     pub last_active_at: String,
     pub status: ProjectSessionStatus,
     pub latest_result: Option<String>,
+    #[serde(default)]
+    pub usage: TokenUsage, // Sum of the tokens reported by this session's agents
+    #[serde(default)]
+    pub rules: VibeRules, // `.vibe/config/rules.json`, read when the session started
+    #[serde(default)]
+    pub orchestrator_lost: bool, // Restored without a live orchestrator; respawned on next use
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -37,7 +46,9 @@ pub async fn create_or_get_session_for_project(
 ) -> ProjectSession {
     // 1. Optimistic read check (sync)
     if let Some(existing) = find_active_session(state, project_root) {
-        return update_last_active(state, &existing.session_id).unwrap_or(existing);
+        if !existing.orchestrator_lost {
+            return update_last_active(state, &existing.session_id).unwrap_or(existing);
+        }
     }
 
     // 2. Write lock scope
@@ -52,17 +63,24 @@ pub async fn create_or_get_session_for_project(
             })
             .cloned()
         {
-            // Found existing, update timestamp inline
+            // Found existing, update timestamp inline; an orchestrator lost
+            // across a restart is respawned for it
             let mut updated = existing.clone();
             updated.last_active_at = Utc::now().to_rfc3339();
+            updated.orchestrator_lost = false;
             if let Some(entry) = sessions.get_mut(&existing.session_id) {
                 entry.last_active_at = updated.last_active_at.clone();
+                entry.orchestrator_lost = false;
             }
-            (updated, false)
+            (updated, existing.orchestrator_lost)
         } else {
             // Create new
             let now = Utc::now().to_rfc3339();
             let session_id = Uuid::new_v4().to_string();
+            let rules = load_rules(Path::new(project_root)).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable rules.json in {}: {}", project_root, e);
+                VibeRules::default()
+            });
             let session = ProjectSession {
                 session_id: session_id.clone(),
                 project_root: project_root.to_string(),
@@ -71,6 +89,9 @@ pub async fn create_or_get_session_for_project(
                 last_active_at: now,
                 status: ProjectSessionStatus::Active,
                 latest_result: None,
+                usage: TokenUsage::default(),
+                rules,
+                orchestrator_lost: false,
             };
             sessions.insert(session_id.clone(), session.clone());
             (session, true)
@@ -90,42 +111,10 @@ pub async fn create_or_get_session_for_project(
         let server_url = format!("http://{}:{}", state.config.host, state.config.http_port);
         let session_id = session.session_id.clone();
 
-        let instruction = format!(
-            r#"You are the Root Orchestrator Vibe agent (ID: {}). Your goal is to plan the development of this project: '{}'.
-
-You have access to the following Vibe utilities, which are executable binaries in your PATH:
-- `vibe-report --agent-id {} --session-id {} --progress <percentage> --thought "<message>"`
-- `vibe-ask --agent-id {} --session-id {} --question "<question>"` (Blocks until user replies)
-- `vibe-complete --agent-id {} --session-id {} --result "<summary>"`
-
-**IMPORTANT:** To use these utilities, you MUST use the `run_shell_command` tool. 
-For example, to ask a question, you would call:
-`run_shell_command(command="vibe-ask --agent-id ... --question ...")`
-
-Do NOT try to call `vibe_ask` as a direct tool function; it will fail.
-
-Your first task is to analyze the project state and interact with the user to define the immediate goals.
-Use `vibe-ask` to gather requirements if they are vague.
-Once you have a clear plan, output a JSON object with a 'tasks' array describing the next steps.
-Each task should have an 'id' (string), 'description' (string), and optional 'agent_type' (string).
-
-Example:
-```json
-{{
-  "tasks": [
-    {{ "id": "init-1", "description": "Create README.md", "agent_type": "worker" }}
-  ]
-}}
-```
-"#,
-            agent_id,
-            project_name,
-            agent_id, session_id,
-            agent_id, session_id,
-            agent_id, session_id
-        );
+        let profile = state.config.prompt_profile.as_deref().and_then(|id| state.profiles.get(id));
+        let instruction = orchestrator_prompt(profile.as_ref(), &session, &agent_id).text;
 
-        let mut env_vars = HashMap::new();
+        let mut env_vars = adapter.get_env();
         if let Ok(key) = env::var("GEMINI_API_KEY") {
             env_vars.insert("GEMINI_API_KEY".to_string(), key);
         }
@@ -156,7 +145,8 @@ Example:
             command,
             args,
             env_vars,
-            Some(agent_id)
+            Some(agent_id),
+            None,
         ).await {
             Ok(spawned_agent_id) => info!("Root Orchestrator agent {} spawned for session {}", spawned_agent_id, session_id),
             Err(e) => error!("Failed to spawn Root Orchestrator for session {}: {}", session_id, e),
@@ -180,6 +170,20 @@ pub fn update_last_active(state: &AppState, session_id: &str) -> Option<ProjectS
     None
 }
 
+/// Recomputes a project session's usage from the agents registered for it.
+pub fn refresh_usage(state: &AppState, session_id: &str) -> Option<TokenUsage> {
+    let usage: TokenUsage = state
+        .agents
+        .list_agents_by_session(session_id)
+        .into_iter()
+        .map(|agent| agent.usage)
+        .sum();
+    let mut sessions = state.project_sessions.write();
+    let session = sessions.get_mut(session_id)?;
+    session.usage = usage;
+    Some(usage)
+}
+
 fn find_active_session(state: &AppState, project_root: &str) -> Option<ProjectSession> {
     let sessions = state.project_sessions.read();
     sessions
@@ -0,0 +1,316 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::agents::boundaries;
+use crate::agents::result::RESULT_JSON_GUIDE;
+use crate::profiles::PromptProfile;
+use crate::project_sessions::ProjectSession;
+use crate::tasks::Task;
+use crate::template::Template;
+use crate::vibe_project::is_scaffold_placeholder;
+
+/// Mode used by agent types that have no mode file of their own.
+const FALLBACK_MODE: &str = "WORKER";
+
+const ORCHESTRATOR_TEMPLATE: &str = r#"You are the Root Orchestrator Vibe agent (ID: {{agent_id}}). Your goal is to plan the development of this project: '{{project.name}}'.
+
+You have access to the following Vibe utilities, which are executable binaries in your PATH:
+- `vibe-report --agent-id {{agent_id}} --session-id {{session_id}} --progress <percentage> --thought "<message>"`
+- `vibe-ask --agent-id {{agent_id}} --session-id {{session_id}} --question "<question>"` (Blocks until user replies)
+  Add `--kind confirm`, `--kind choice --option <a> --option <b>`, `--kind multi-choice ...` or `--kind file-approval --file <path>` for a structured answer, and `--default <answer> --timeout-secs <n>` to stop waiting; exit code 3 means the default was used.
+- `vibe-complete --agent-id {{agent_id}} --session-id {{session_id}} --result "<summary>"`
+- `vibe-spawn --agent-id {{agent_id}} --session-id {{session_id}} --description "<task>"` (Queues a sub-agent for the task and prints its task id)
+
+**IMPORTANT:** To use these utilities, you MUST use the `run_shell_command` tool. 
+For example, to ask a question, you would call:
+`run_shell_command(command="vibe-ask --agent-id ... --question ...")`
+
+Do NOT try to call `vibe_ask` as a direct tool function; it will fail.
+
+Your first task is to analyze the project state and interact with the user to define the immediate goals.
+Use `vibe-ask` to gather requirements if they are vague.
+Once you have a clear plan, output a JSON object with a 'tasks' array describing the next steps
+(or list them as `follow_up_tasks` in your RESULT.json).
+Each task should have an 'id' (string), 'description' (string), optional 'agent_type' (string),
+optional 'depends_on' (array of task ids that must complete before the task starts),
+optional 'timeout_secs' (number of seconds the task may run before it is stopped),
+optional 'max_attempts' / 'backoff_secs' (how often a failing task is retried, and how long to wait first),
+and optional 'must_touch' / 'may_touch' (path globs such as "src/**/*.rs" the worker is expected / allowed to change;
+changes to other files are flagged as boundary violations).
+
+Example:
+```json
+{
+  "tasks": [
+    { "id": "init-1", "description": "Create README.md", "agent_type": "worker" },
+    { "id": "init-2", "description": "Document the build steps in README.md", "agent_type": "worker", "depends_on": ["init-1"] }
+  ]
+}
+```
+"#;
+
+const WORKER_TEMPLATE: &str = r#"You are a Vibe agent named {{agent_id}}. Your task is to {{task.description}}.
+
+You have access to the following Vibe utilities, which are executable binaries in your PATH:
+- `vibe-report --agent-id {{agent_id}} --session-id {{session_id}} --progress <percentage> --thought "<message>"`
+- `vibe-ask --agent-id {{agent_id}} --session-id {{session_id}} --question "<question>"` (Blocks until user replies)
+  Add `--kind confirm`, `--kind choice --option <a> --option <b>`, `--kind multi-choice ...` or `--kind file-approval --file <path>` for a structured answer, and `--default <answer> --timeout-secs <n>` to stop waiting; exit code 3 means the default was used.
+- `vibe-complete --agent-id {{agent_id}} --session-id {{session_id}} --result "<summary>"`
+- `vibe-spawn --agent-id {{agent_id}} --session-id {{session_id}} --description "<task>"` (Queues a sub-agent for the task and prints its task id)
+
+**IMPORTANT:** To use these utilities, you MUST use the `run_shell_command` tool. 
+For example: `run_shell_command(command="vibe-complete --agent-id ...")`
+
+Do NOT try to call these as direct tool functions.
+
+You should use your tools to perform the task. When you believe you have successfully completed the task, use `vibe-complete`.
+
+Task: {{task.description}}
+{{previous_results}}"#;
+
+/// One source that contributed to an agent's instructions.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptLayer {
+    pub source: String, // e.g. "profile:default/MODES/WORKER.md" or ".vibe/AGENTS.md"
+    pub content: String,
+}
+
+/// The instruction an agent receives, with the layers it was built from.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedPrompt {
+    pub agent_type: String,
+    pub mode: Option<String>, // None when neither the profile nor the project defines one
+    pub layers: Vec<PromptLayer>,
+    pub text: String,
+}
+
+impl ResolvedPrompt {
+    /// Layers profile and project context in front of the agent's own instructions:
+    /// profile `AGENTS.md`, profile mode, project `.vibe/AGENTS.md`,
+    /// `.vibe/DOCUMENTATION.md` and `.vibe/MODES/<MODE>.md`. Files still holding
+    /// the scaffold's placeholder text are skipped. The files are rendered as
+    /// templates with `values`; `body` is used as given.
+    pub fn assemble(
+        profile: Option<&PromptProfile>,
+        project_root: Option<&Path>,
+        agent_type: &str,
+        body: String,
+        values: &HashMap<&str, String>,
+    ) -> Self {
+        let vibe_dir = project_root.map(|root| root.join(".vibe"));
+        let project_mode = |mode: &str| {
+            vibe_dir
+                .as_ref()
+                .and_then(|dir| read_layer(&dir.join("MODES").join(format!("{}.md", mode))))
+        };
+        let profile_mode = |mode: &str| profile.and_then(|p| p.modes.get(mode)).filter(|c| !c.trim().is_empty());
+
+        let mut candidates = vec![mode_name(agent_type)];
+        if candidates[0] != "ORCHESTRATOR" && candidates[0] != FALLBACK_MODE {
+            candidates.push(FALLBACK_MODE.to_string());
+        }
+        let mode = candidates
+            .into_iter()
+            .find(|mode| profile_mode(mode).is_some() || project_mode(mode).is_some());
+
+        let mut layers = Vec::new();
+        if let Some(profile) = profile {
+            if !profile.agents_doc.trim().is_empty() {
+                layers.push(PromptLayer {
+                    source: format!("profile:{}/AGENTS.md", profile.id),
+                    content: profile.agents_doc.clone(),
+                });
+            }
+            if let Some(content) = mode.as_deref().and_then(profile_mode) {
+                layers.push(PromptLayer {
+                    source: format!("profile:{}/MODES/{}.md", profile.id, mode.as_deref().unwrap_or_default()),
+                    content: content.clone(),
+                });
+            }
+        }
+        if let Some(dir) = &vibe_dir {
+            for file in ["AGENTS.md", "DOCUMENTATION.md"] {
+                if let Some(content) = read_layer(&dir.join(file)) {
+                    layers.push(PromptLayer {
+                        source: format!(".vibe/{}", file),
+                        content,
+                    });
+                }
+            }
+        }
+        if let Some(mode) = &mode {
+            if let Some(content) = project_mode(mode) {
+                layers.push(PromptLayer {
+                    source: format!(".vibe/MODES/{}.md", mode),
+                    content,
+                });
+            }
+        }
+        for layer in &mut layers {
+            layer.content = render_layer(&layer.source, &layer.content, values);
+        }
+        layers.push(PromptLayer {
+            source: "instructions".to_string(),
+            content: body,
+        });
+
+        let text = layers
+            .iter()
+            .map(|layer| layer.content.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+            + "\n";
+        Self {
+            agent_type: agent_type.to_string(),
+            mode,
+            layers,
+            text,
+        }
+    }
+}
+
+/// Mode file stem for an agent type, e.g. `doc-scribe` -> `DOC_SCRIBE`.
+pub fn mode_name(agent_type: &str) -> String {
+    agent_type.to_uppercase().replace(['-', ' '], "_")
+}
+
+fn builtin_template(source: &str) -> Template {
+    Template::parse(source).expect("built-in prompt templates are valid")
+}
+
+/// Renders a layer, keeping it verbatim if it is not a valid template.
+/// Profiles are validated when they load, so this only happens for project files.
+fn render_layer(source: &str, content: &str, values: &HashMap<&str, String>) -> String {
+    match Template::parse(content) {
+        Ok(template) => template.render(values),
+        Err(e) => {
+            warn!("Using {} without substitutions: {}", source, e);
+            content.to_string()
+        }
+    }
+}
+
+/// Template values for an agent. Those that do not apply are left out and render empty.
+fn variables(
+    agent_id: &str,
+    session_id: &str,
+    session: Option<&ProjectSession>,
+    task: Option<&Task>,
+    previous_results: Option<&str>,
+) -> HashMap<&'static str, String> {
+    let mut values = HashMap::from([("agent_id", agent_id.to_string()), ("session_id", session_id.to_string())]);
+    if let Some(session) = session {
+        values.insert("project.name", session.project_name.clone());
+        values.insert("project.root", session.project_root.clone());
+    }
+    if let Some(task) = task {
+        values.insert("task.id", task.id.clone());
+        values.insert("task.description", task.description.clone());
+    }
+    if let Some(previous_results) = previous_results {
+        values.insert("previous_results", previous_results.to_string());
+    }
+    values
+}
+
+fn read_layer(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    if content.trim().is_empty() || is_scaffold_placeholder(&content) {
+        return None;
+    }
+    Some(content)
+}
+
+/// Instructions for the root orchestrator of a project session.
+pub fn orchestrator_prompt(profile: Option<&PromptProfile>, session: &ProjectSession, agent_id: &str) -> ResolvedPrompt {
+    let values = variables(agent_id, &session.session_id, Some(session), None, None);
+    let mut body = builtin_template(ORCHESTRATOR_TEMPLATE).render(&values);
+    body.push_str(RESULT_JSON_GUIDE);
+    ResolvedPrompt::assemble(profile, Some(Path::new(&session.project_root)), "orchestrator", body, &values)
+}
+
+/// Instructions for an agent working on `task`. `session` supplies the project
+/// context and rules when the session is known; `previous_results` describes
+/// an earlier, failed attempt at the task.
+pub fn task_prompt(
+    profile: Option<&PromptProfile>,
+    session_id: &str,
+    session: Option<&ProjectSession>,
+    agent_id: &str,
+    task: &Task,
+    previous_results: Option<&str>,
+) -> ResolvedPrompt {
+    let values = variables(agent_id, session_id, session, Some(task), previous_results);
+    let mut body = builtin_template(WORKER_TEMPLATE).render(&values);
+    if let Some(boundaries) = boundaries::instructions(task) {
+        body.push_str(&boundaries);
+    }
+    if let Some(rules) = session.and_then(|s| s.rules.instructions()) {
+        body.push_str(&rules);
+    }
+    body.push_str(RESULT_JSON_GUIDE);
+
+    let agent_type = task.agent_type.as_deref().unwrap_or("worker");
+    let project_root = session.map(|s| Path::new(&s.project_root));
+    ResolvedPrompt::assemble(profile, project_root, agent_type, body, &values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_layers_profile_and_project_context() {
+        let dir = tempdir().unwrap();
+        let vibe = dir.path().join(".vibe");
+        fs::create_dir_all(vibe.join("MODES")).unwrap();
+        fs::write(vibe.join("AGENTS.md"), "# Agents\n\nProject agents.\n").unwrap();
+        fs::write(vibe.join("DOCUMENTATION.md"), "# Documentation\n\nTODO: Document shared context for this project.\n").unwrap();
+        fs::write(vibe.join("MODES").join("WORKER.md"), "Worker mode for {{agent_id}}.\n").unwrap();
+        let profile = PromptProfile {
+            id: "default".to_string(),
+            name: "Default".to_string(),
+            description: None,
+            agents_doc: "Profile agents.\n".to_string(),
+            modes: HashMap::from([("WORKER".to_string(), "Profile worker mode.\n".to_string())]),
+        };
+
+        let values = HashMap::from([("agent_id", "a1".to_string())]);
+
+        // A resolver has no mode of its own and falls back to WORKER
+        let prompt = ResolvedPrompt::assemble(Some(&profile), Some(dir.path()), "resolver", "Do it.".to_string(), &values);
+        assert_eq!(prompt.mode.as_deref(), Some("WORKER"));
+        let sources: Vec<&str> = prompt.layers.iter().map(|layer| layer.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                "profile:default/AGENTS.md",
+                "profile:default/MODES/WORKER.md",
+                ".vibe/AGENTS.md",
+                ".vibe/MODES/WORKER.md",
+                "instructions"
+            ]
+        );
+        assert!(prompt.text.starts_with("Profile agents."));
+        assert!(prompt.text.contains("Worker mode for a1."));
+        assert!(prompt.text.ends_with("Do it.\n"));
+
+        let bare = ResolvedPrompt::assemble(None, None, "orchestrator", "Plan.".to_string(), &HashMap::new());
+        assert!(bare.mode.is_none());
+        assert_eq!(bare.text, "Plan.\n");
+    }
+
+    #[test]
+    fn test_task_prompt_substitutes_variables() {
+        let task: Task = serde_json::from_str(r#"{"id": "t1", "description": "write the docs", "agent_type": null}"#).unwrap();
+        let previous = "\n## Previous attempt\n\nAttempt 1 of this task failed: exited with status 1\n";
+        let prompt = task_prompt(None, "s1", None, "a1", &task, Some(previous));
+        assert!(prompt.text.starts_with("You are a Vibe agent named a1. Your task is to write the docs."));
+        assert!(prompt.text.contains("vibe-complete --agent-id a1 --session-id s1"));
+        assert!(prompt.text.contains("Attempt 1 of this task failed"));
+        assert!(!prompt.text.contains("{{"));
+    }
+}
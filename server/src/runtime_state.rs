@@ -0,0 +1,244 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{
+    agents::{
+        dispatcher::TaskDispatcher,
+        registry::{Agent, AgentStatus},
+    },
+    project_sessions::ProjectSession,
+    state::AppState,
+    tasks::TaskSchedule,
+    utils::process::{process_start_time, spawn_and_capture_output},
+};
+
+pub const RUNTIME_STATE_VERSION: u32 = 1;
+pub const RUNTIME_STATE_FILE: &str = "server_state.json";
+
+/// Everything the server knows about one project, as stored in
+/// `<project_root>/.vibe/runtime/server_state.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeSnapshot {
+    pub version: u32,
+    pub sessions: Vec<ProjectSession>,
+    pub agents: Vec<Agent>,
+    #[serde(default)]
+    pub schedules: BTreeMap<String, TaskSchedule>, // session_id -> task DAG
+}
+
+#[derive(Debug, Error)]
+pub enum RuntimeStateError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] serde_json::Error),
+}
+
+pub fn runtime_state_path(project_root: &Path) -> PathBuf {
+    project_root
+        .join(".vibe")
+        .join("runtime")
+        .join(RUNTIME_STATE_FILE)
+}
+
+pub fn load_snapshot(project_root: &Path) -> Result<Option<RuntimeSnapshot>, RuntimeStateError> {
+    let path = runtime_state_path(project_root);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let raw = fs::read_to_string(&path)?;
+    Ok(Some(serde_json::from_str(&raw)?))
+}
+
+/// Writes the snapshot via a temporary file so a crash mid-write leaves the
+/// previous snapshot intact.
+pub fn save_snapshot(project_root: &Path, snapshot: &RuntimeSnapshot) -> Result<(), RuntimeStateError> {
+    let path = runtime_state_path(project_root);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_vec_pretty(snapshot)?)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Snapshots every project that has sessions and an initialized `.vibe/` directory.
+pub fn snapshot_projects(state: &AppState, dispatcher: &TaskDispatcher) {
+    let mut by_project: HashMap<String, Vec<ProjectSession>> = HashMap::new();
+    for session in state.project_sessions.read().values() {
+        by_project
+            .entry(session.project_root.clone())
+            .or_default()
+            .push(session.clone());
+    }
+
+    for (project_root, mut sessions) in by_project {
+        let root = Path::new(&project_root);
+        if !root.join(".vibe").is_dir() {
+            continue;
+        }
+        sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+        let mut agents = Vec::new();
+        let mut schedules = BTreeMap::new();
+        for session in &sessions {
+            agents.extend(state.agents.list_agents_by_session(&session.session_id));
+            if let Some(schedule) = dispatcher.schedule_snapshot(&session.session_id) {
+                schedules.insert(session.session_id.clone(), schedule);
+            }
+        }
+        agents.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let snapshot = RuntimeSnapshot {
+            version: RUNTIME_STATE_VERSION,
+            sessions,
+            agents,
+            schedules,
+        };
+        if let Err(err) = save_snapshot(root, &snapshot) {
+            warn!("Failed to snapshot runtime state for {}: {err}", project_root);
+        }
+    }
+}
+
+/// Loads the snapshots of all registered projects back into the server state.
+/// Agents whose process is gone are marked failed; live ones are re-attached by
+/// PID and watched until they exit. Sessions that lose their orchestrator this
+/// way are flagged so the next request for the project respawns it.
+pub fn restore_projects(state: &AppState, dispatcher: &TaskDispatcher) {
+    let project_roots: Vec<String> = state
+        .global_registry
+        .read()
+        .projects
+        .iter()
+        .map(|project| project.project_root.clone())
+        .collect();
+
+    for project_root in project_roots {
+        let snapshot = match load_snapshot(Path::new(&project_root)) {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => continue,
+            Err(err) => {
+                warn!("Ignoring unreadable runtime state for {}: {err}", project_root);
+                continue;
+            }
+        };
+
+        {
+            let mut sessions = state.project_sessions.write();
+            for session in snapshot.sessions {
+                sessions.insert(session.session_id.clone(), session);
+            }
+        }
+
+        for mut agent in snapshot.agents {
+            let mut reattached = false;
+            if agent.status.is_active() {
+                match agent.pid {
+                    Some(pid) if same_process(pid, agent.process_started.as_deref()) => {
+                        info!("Re-attached agent {} (pid {})", agent.id, pid);
+                        reattached = true;
+                    }
+                    _ => {
+                        agent.status = AgentStatus::Failed(
+                            "agent process was lost across a server restart".to_string(),
+                        );
+                        if agent.agent_type == "orchestrator" {
+                            if let Some(session) =
+                                state.project_sessions.write().get_mut(&agent.session_id)
+                            {
+                                session.orchestrator_lost = true;
+                            }
+                        }
+                    }
+                }
+            }
+            let agent_id = agent.id.clone();
+            state.agents.register_agent(agent);
+            if reattached {
+                state.agent_spawner.reattach(&agent_id);
+            }
+        }
+
+        for (session_id, schedule) in snapshot.schedules {
+            dispatcher.restore_schedule(&session_id, schedule);
+        }
+        info!("Restored runtime state for {}", project_root);
+    }
+}
+
+fn process_alive(pid: u32) -> bool {
+    spawn_and_capture_output("kill", &["-0", &pid.to_string()]).is_ok()
+}
+
+/// Whether `pid` is still the process that was recorded with start time
+/// `started`, rather than a new process that was handed the same pid.
+fn same_process(pid: u32, started: Option<&str>) -> bool {
+    process_alive(pid)
+        && started.is_none_or(|started| process_start_time(pid).as_deref() == Some(started))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_sessions::ProjectSessionStatus;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = tempdir().unwrap();
+        let mut agent = Agent::new("session-1".to_string(), "worker".to_string());
+        agent.status = AgentStatus::Failed("boom".to_string());
+        let snapshot = RuntimeSnapshot {
+            version: RUNTIME_STATE_VERSION,
+            sessions: vec![ProjectSession {
+                session_id: "session-1".to_string(),
+                project_root: dir.path().to_string_lossy().to_string(),
+                project_name: "demo".to_string(),
+                created_at: "2025-11-18T16:00:00Z".to_string(),
+                last_active_at: "2025-11-18T16:00:00Z".to_string(),
+                status: ProjectSessionStatus::Active,
+                latest_result: Some("done".to_string()),
+                usage: Default::default(),
+                rules: Default::default(),
+                orchestrator_lost: false,
+            }],
+            agents: vec![agent.clone()],
+            schedules: BTreeMap::new(),
+        };
+
+        save_snapshot(dir.path(), &snapshot).unwrap();
+        let loaded = load_snapshot(dir.path()).unwrap().expect("snapshot exists");
+        assert_eq!(loaded.sessions[0].latest_result.as_deref(), Some("done"));
+        assert_eq!(loaded.agents[0].id, agent.id);
+        assert_eq!(loaded.agents[0].status, agent.status);
+    }
+
+    #[test]
+    fn test_missing_snapshot_is_none() {
+        let dir = tempdir().unwrap();
+        assert!(load_snapshot(dir.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_process_alive_detects_own_process() {
+        assert!(process_alive(std::process::id()));
+    }
+
+    #[test]
+    fn test_reused_pid_is_not_the_same_process() {
+        let pid = std::process::id();
+        let started = process_start_time(pid).unwrap();
+        assert!(same_process(pid, Some(&started)));
+        assert!(same_process(pid, None));
+        assert!(!same_process(pid, Some("Thu Jan  1 00:00:00 1970")));
+    }
+}
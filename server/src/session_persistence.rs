@@ -0,0 +1,219 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tracing::{error, warn};
+
+use crate::{
+    global_registry::global_home_dir,
+    sessions::{Session, SessionMessage},
+};
+
+/// A change to the session store, recorded in the order it happened.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SessionRecord {
+    Created {
+        session: Session,
+    },
+    /// Inserts the message, or replaces an earlier message with the same id.
+    #[serde(rename_all = "camelCase")]
+    Message {
+        session_id: String,
+        message: SessionMessage,
+        updated_at: DateTime<Utc>,
+    },
+    Deleted {
+        id: String,
+    },
+}
+
+/// Storage backend that `SessionStore` writes through to.
+pub trait SessionPersistence: Send + Sync {
+    fn append(&self, record: &SessionRecord) -> io::Result<()>;
+    fn load(&self) -> io::Result<Vec<SessionRecord>>;
+}
+
+/// Append-only JSONL log, one `SessionRecord` per line.
+pub struct JsonlSessionLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl JsonlSessionLog {
+    /// Opens the log at `path` for appending, creating it and its directory
+    /// if needed.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Opens the default log at `<global home>/sessions.jsonl`.
+    pub fn open_default() -> io::Result<Self> {
+        Self::open(global_home_dir()?.join("sessions.jsonl"))
+    }
+}
+
+impl SessionPersistence for JsonlSessionLog {
+    fn append(&self, record: &SessionRecord) -> io::Result<()> {
+        let mut line = serde_json::to_string(record).map_err(|err| io::Error::other(err.to_string()))?;
+        line.push('\n');
+        self.file.lock().write_all(line.as_bytes())
+    }
+
+    fn load(&self) -> io::Result<Vec<SessionRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = fs::File::open(&self.path)?;
+        let mut records = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A torn final line after a crash should not take the whole history with it.
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(err) => warn!(
+                    "Skipping unreadable record on line {} of {}: {err}",
+                    index + 1,
+                    self.path.display()
+                ),
+            }
+        }
+        Ok(records)
+    }
+}
+
+enum WriterCommand {
+    Append(SessionRecord),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Appends records on a dedicated thread, in the order they were queued, so
+/// the session store never waits on the disk while holding its locks.
+#[derive(Clone)]
+pub struct SessionWriter {
+    commands: mpsc::Sender<WriterCommand>,
+}
+
+impl SessionWriter {
+    pub fn spawn(persistence: Arc<dyn SessionPersistence>) -> io::Result<Self> {
+        let (commands, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("session-writer".to_string())
+            .spawn(move || {
+                for command in receiver {
+                    match command {
+                        WriterCommand::Append(record) => {
+                            if let Err(err) = persistence.append(&record) {
+                                error!("Failed to persist session record: {err}");
+                            }
+                        }
+                        WriterCommand::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            })?;
+        Ok(Self { commands })
+    }
+
+    pub fn append(&self, record: SessionRecord) {
+        if self.commands.send(WriterCommand::Append(record)).is_err() {
+            error!("Session writer has stopped; dropping session record");
+        }
+    }
+
+    /// Waits until every record queued so far has been written.
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if self.commands.send(WriterCommand::Flush(done)).is_ok() {
+            let _ = written.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        llm::{LlmConfig, MessageRole, ProviderKind},
+        sessions::{SessionCreateParams, SessionStore},
+        usage::TokenUsage,
+    };
+    use tempfile::tempdir;
+
+    fn params(name: &str) -> SessionCreateParams {
+        SessionCreateParams {
+            name: name.to_string(),
+            profile: "default".to_string(),
+            llm_config: LlmConfig {
+                provider: ProviderKind::Dummy,
+                model: "dummy".into(),
+                temperature: 0.2,
+            },
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_survive_reload() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sessions.jsonl");
+
+        let store = SessionStore::with_persistence(Arc::new(JsonlSessionLog::open(&path).unwrap())).unwrap();
+        let kept = store.create(params("kept")).await;
+        let dropped = store.create(params("dropped")).await;
+        store
+            .append_message(&kept.id, MessageRole::User, "hello".to_string())
+            .await
+            .unwrap();
+        store
+            .update_assistant_message(&kept.id, "reply-1", "partial", None)
+            .await;
+        store
+            .update_assistant_message(&kept.id, "reply-1", "full answer", Some(TokenUsage::new(3, 2)))
+            .await;
+        assert!(store.delete(&dropped.id).await);
+        store.flush().await;
+
+        let reloaded = SessionStore::with_persistence(Arc::new(JsonlSessionLog::open(&path).unwrap())).unwrap();
+        let sessions = reloaded.list().await;
+        assert_eq!(sessions.len(), 1);
+        let detail = reloaded.detail(&kept.id).await.expect("session was replayed");
+        assert_eq!(detail.name, "kept");
+        assert_eq!(detail.messages.len(), 2);
+        assert_eq!(detail.messages[1].content, "full answer");
+        assert_eq!(sessions[0].usage, TokenUsage::new(3, 2));
+    }
+
+    #[test]
+    fn test_load_skips_torn_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("sessions.jsonl");
+        fs::write(&path, "{\"type\":\"deleted\",\"id\":\"a\"}\n{\"type\":\"crea").unwrap();
+
+        let records = JsonlSessionLog::open(&path).unwrap().load().unwrap();
+        assert_eq!(records.len(), 1);
+    }
+}
@@ -1,17 +1,28 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, io, sync::Arc};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::sync::{broadcast, RwLock};
+use tracing::warn;
 use uuid::Uuid;
 
-use crate::llm::{LlmConfig, MessageRole};
+use crate::{
+    agents::{
+        events::{AgentEvent, AgentEventBus, AgentOutput, OutputStream},
+        registry::AgentStatus,
+    },
+    llm::{LlmConfig, MessageRole},
+    profiles::{InvalidProfile, ProfileSummary},
+    session_persistence::{SessionPersistence, SessionRecord, SessionWriter},
+    usage::TokenUsage,
+};
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct SessionStore {
     sessions: Arc<RwLock<HashMap<String, Session>>>,
     channels: Arc<RwLock<HashMap<String, broadcast::Sender<WsEvent>>>>,
+    persistence: Option<SessionWriter>,
 }
 
 impl SessionStore {
@@ -19,6 +30,40 @@ impl SessionStore {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             channels: Arc::new(RwLock::new(HashMap::new())),
+            persistence: None,
+        }
+    }
+
+    /// Creates a store backed by `persistence`, replaying its records so
+    /// sessions and their messages survive restarts.
+    pub fn with_persistence(persistence: Arc<dyn SessionPersistence>) -> io::Result<Self> {
+        let mut sessions = HashMap::new();
+        for record in persistence.load()? {
+            apply_record(&mut sessions, record);
+        }
+        let channels = sessions
+            .keys()
+            .map(|id| (id.clone(), broadcast::channel(128).0))
+            .collect();
+        Ok(Self {
+            sessions: Arc::new(RwLock::new(sessions)),
+            channels: Arc::new(RwLock::new(channels)),
+            persistence: Some(SessionWriter::spawn(persistence)?),
+        })
+    }
+
+    /// Queues `record` for the writer. Callers queue while still holding the
+    /// sessions lock so records land in the same order as the changes.
+    fn persist(&self, record: SessionRecord) {
+        if let Some(persistence) = &self.persistence {
+            persistence.append(record);
+        }
+    }
+
+    /// Waits until every change made so far has been persisted.
+    pub async fn flush(&self) {
+        if let Some(persistence) = &self.persistence {
+            persistence.flush().await;
         }
     }
 
@@ -47,6 +92,9 @@ impl SessionStore {
             meta: params.meta.unwrap_or(Value::Object(Default::default())),
         };
         let detail = session.detail();
+        self.persist(SessionRecord::Created {
+            session: session.clone(),
+        });
         sessions.insert(id.clone(), session);
         drop(sessions);
         self.ensure_channel(&id).await;
@@ -56,6 +104,9 @@ impl SessionStore {
     pub async fn delete(&self, id: &str) -> bool {
         let mut sessions = self.sessions.write().await;
         let removed = sessions.remove(id).is_some();
+        if removed {
+            self.persist(SessionRecord::Deleted { id: id.to_string() });
+        }
         drop(sessions);
         if removed {
             let mut channels = self.channels.write().await;
@@ -86,6 +137,11 @@ impl SessionStore {
         };
         session.messages.push(message.clone());
         session.updated_at = Utc::now();
+        self.persist(SessionRecord::Message {
+            session_id: session_id.to_string(),
+            message: message.clone(),
+            updated_at: session.updated_at,
+        });
         Some(message)
     }
 
@@ -94,6 +150,7 @@ impl SessionStore {
         session_id: &str,
         message_id: &str,
         content: &str,
+        usage: Option<TokenUsage>,
     ) {
         let mut sessions = self.sessions.write().await;
         if let Some(session) = sessions.get_mut(session_id) {
@@ -113,6 +170,19 @@ impl SessionStore {
                     meta: Value::Null,
                 });
             }
+            if let Some(message) = session.messages.iter_mut().find(|m| m.id == message_id) {
+                if let Some(usage) = usage {
+                    if !message.meta.is_object() {
+                        message.meta = Value::Object(Default::default());
+                    }
+                    message.meta["usage"] = serde_json::to_value(usage).unwrap_or_default();
+                }
+                self.persist(SessionRecord::Message {
+                    session_id: session_id.to_string(),
+                    message: message.clone(),
+                    updated_at: session.updated_at,
+                });
+            }
         }
     }
 
@@ -150,6 +220,49 @@ impl SessionStore {
         }
     }
 
+    /// Sends `event` to the subscribers of every session.
+    pub async fn broadcast(&self, event: WsEvent) {
+        let senders: Vec<_> = self.channels.read().await.values().cloned().collect();
+        for sender in senders {
+            let _ = sender.send(event.clone());
+        }
+    }
+
+    /// Relays agent lifecycle events to the owning session's subscribers as
+    /// `AgentStatusUpdate`s, and output lines as `AgentOutput`.
+    pub fn forward_agent_events(&self, events: &AgentEventBus) {
+        let store = self.clone();
+        let mut receiver = events.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => store.publish(event.session_id(), agent_status_update(&event)).await,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Dropped {} agent events for WebSocket subscribers", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let store = self.clone();
+        let mut receiver = events.subscribe_output();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(output) => {
+                        let session_id = output.session_id.clone();
+                        store.publish(&session_id, agent_output(output)).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Dropped {} agent output lines for WebSocket subscribers", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
     pub async fn summary(&self, session_id: &str) -> Option<SessionSummary> {
         let sessions = self.sessions.read().await;
         sessions.get(session_id).map(Session::summary)
@@ -181,6 +294,7 @@ pub struct SessionSummary {
     pub updated_at: DateTime<Utc>,
     pub llm_config: LlmConfig,
     pub meta: Value,
+    pub usage: TokenUsage,
 }
 
 #[derive(Clone, Serialize)]
@@ -236,11 +350,69 @@ pub enum WsEvent {
             thought: Option<String>,
             result: Option<String>,
         },
+        AgentOutput {
+            session_id: String,
+            agent_id: String,
+            stream: OutputStream,
+            line: String,
+            seq: u64,
+        },
+        TaskFailed {
+            session_id: String,
+            task_id: String,
+            attempts: u32,
+            reason: String,
+        },
+        ProfilesChanged {
+            profiles: Vec<ProfileSummary>,
+            invalid: Vec<InvalidProfile>,
+        },
         Error {                                                                     
             code: String,                                                           
             message: String,                                                        
         },}
 
+fn agent_output(output: AgentOutput) -> WsEvent {
+    WsEvent::AgentOutput {
+        session_id: output.session_id,
+        agent_id: output.agent_id,
+        stream: output.stream,
+        line: output.line,
+        seq: output.seq,
+    }
+}
+
+/// Maps a lifecycle event to the status update clients render.
+fn agent_status_update(event: &AgentEvent) -> WsEvent {
+    let (status, progress, thought, result) = match event {
+        AgentEvent::Spawned { .. } => ("running".to_string(), 0, None, None),
+        AgentEvent::Reported { progress, thought, .. } => ("running".to_string(), *progress, thought.clone(), None),
+        AgentEvent::Asked { .. } => (
+            "waiting_for_interaction".to_string(),
+            0,
+            Some("Waiting for user input...".to_string()),
+            None,
+        ),
+        AgentEvent::Completed { result, .. } => ("completed".to_string(), 100, None, result.clone()),
+        AgentEvent::Exited { status, .. } | AgentEvent::StatusChanged { status, .. } => {
+            let thought = match status {
+                AgentStatus::Failed(reason) => Some(reason.clone()),
+                _ => None,
+            };
+            (status.label().to_string(), 0, thought, None)
+        }
+        AgentEvent::Failed { reason, .. } => ("failed".to_string(), 0, Some(reason.clone()), None),
+    };
+    WsEvent::AgentStatusUpdate {
+        session_id: event.session_id().to_string(),
+        agent_id: event.agent_id().to_string(),
+        status,
+        progress,
+        thought,
+        result,
+    }
+}
+
 impl Session {
     fn summary(&self) -> SessionSummary {
         SessionSummary {
@@ -251,9 +423,18 @@ impl Session {
             updated_at: self.updated_at,
             llm_config: self.llm_config.clone(),
             meta: self.meta.clone(),
+            usage: self.usage(),
         }
     }
 
+    /// Sums the token usage recorded on this session's messages.
+    fn usage(&self) -> TokenUsage {
+        self.messages
+            .iter()
+            .filter_map(|message| TokenUsage::from_meta(&message.meta))
+            .sum()
+    }
+
     fn detail(&self) -> SessionDetail {
         SessionDetail {
             id: self.id.clone(),
@@ -268,8 +449,34 @@ impl Session {
     }
 }
 
-#[derive(Clone)]
-struct Session {
+/// Rebuilds in-memory state from a single persisted record.
+fn apply_record(sessions: &mut HashMap<String, Session>, record: SessionRecord) {
+    match record {
+        SessionRecord::Created { session } => {
+            sessions.insert(session.id.clone(), session);
+        }
+        SessionRecord::Message {
+            session_id,
+            message,
+            updated_at,
+        } => {
+            if let Some(session) = sessions.get_mut(&session_id) {
+                match session.messages.iter_mut().find(|m| m.id == message.id) {
+                    Some(existing) => *existing = message,
+                    None => session.messages.push(message),
+                }
+                session.updated_at = updated_at;
+            }
+        }
+        SessionRecord::Deleted { id } => {
+            sessions.remove(&id);
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
     pub id: String,
     pub name: String,
     pub profile: String,
@@ -279,3 +486,55 @@ struct Session {
     pub messages: Vec<SessionMessage>,
     pub meta: Value,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_agent_events_reach_session_subscribers() {
+        let store = SessionStore::new();
+        let bus = AgentEventBus::new();
+        store.forward_agent_events(&bus);
+        let mut receiver = store.subscribe("session-1").await.expect("channel is created");
+
+        bus.publish_output(AgentOutput {
+            agent_id: "agent-1".to_string(),
+            session_id: "session-1".to_string(),
+            stream: OutputStream::Stdout,
+            seq: 0,
+            line: "compiling".to_string(),
+        });
+        bus.publish(AgentEvent::Exited {
+            agent_id: "agent-1".to_string(),
+            session_id: "session-1".to_string(),
+            code: Some(2),
+            status: AgentStatus::Failed("Exited with status: 2".to_string()),
+        });
+
+        // Output and lifecycle events travel separately, so either may come first
+        let (mut output, mut update) = (None, None);
+        for _ in 0..2 {
+            match receiver.recv().await.unwrap() {
+                event @ WsEvent::AgentOutput { .. } => output = Some(event),
+                event @ WsEvent::AgentStatusUpdate { .. } => update = Some(event),
+                _ => panic!("expected agent output or a status update"),
+            }
+        }
+        match output {
+            Some(WsEvent::AgentOutput { line, seq, .. }) => {
+                assert_eq!(line, "compiling");
+                assert_eq!(seq, 0);
+            }
+            _ => panic!("expected an agent output line"),
+        }
+        match update {
+            Some(WsEvent::AgentStatusUpdate { agent_id, status, thought, .. }) => {
+                assert_eq!(agent_id, "agent-1");
+                assert_eq!(status, "failed");
+                assert_eq!(thought.as_deref(), Some("Exited with status: 2"));
+            }
+            _ => panic!("expected an agent status update"),
+        }
+    }
+}
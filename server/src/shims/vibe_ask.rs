@@ -1,7 +1,13 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::{env, thread, time::Duration};
+use std::{env, time::{Duration, Instant}};
+
+/// Exit code when the deadline passed; the default answer, if any, is printed.
+const TIMED_OUT_EXIT_CODE: i32 = 3;
+
+/// Seconds each long-poll asks the server to hold the request open.
+const WAIT_SECS: u64 = 30;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -14,6 +20,45 @@ struct Args {
 
     #[arg(long)]
     question: String,
+
+    /// The kind of answer expected
+    #[arg(long, value_enum, default_value_t = Kind::Text)]
+    kind: Kind,
+
+    /// An option for `choice` and `multi-choice`; repeat for several
+    #[arg(long = "option")]
+    options: Vec<String>,
+
+    /// A file to approve for `file-approval`; repeat for several
+    #[arg(long = "file")]
+    files: Vec<String>,
+
+    /// Answer used when nobody replies before the timeout
+    #[arg(long)]
+    default: Option<String>,
+
+    /// Seconds to wait for an answer; waits indefinitely if unset
+    #[arg(long)]
+    timeout_secs: Option<u64>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Kind {
+    Text,
+    Confirm,
+    Choice,
+    MultiChoice,
+    FileApproval,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum InteractionKind {
+    FreeText,
+    Confirm,
+    SingleChoice { options: Vec<String> },
+    MultiChoice { options: Vec<String> },
+    FileApproval { files: Vec<String> },
 }
 
 #[derive(Serialize)]
@@ -21,6 +66,9 @@ struct AskPayload {
     agent_id: String,
     session_id: String,
     question: String,
+    kind: InteractionKind,
+    default_answer: Option<String>,
+    timeout_secs: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -30,7 +78,7 @@ struct AskResponse {
 
 #[derive(Deserialize, Debug)]
 struct InteractionStatus {
-    status: String, // "Pending" or "Resolved"
+    status: String, // "Pending", "Resolved" or "Expired"
     result: Option<String>,
 }
 
@@ -41,6 +89,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let server_url = env::var("VIBE_SERVER_URL").unwrap_or_else(|_| "http://localhost:4110".to_string());
     let client = Client::new();
 
+    let kind = match args.kind {
+        Kind::Text => InteractionKind::FreeText,
+        Kind::Confirm => InteractionKind::Confirm,
+        Kind::Choice => InteractionKind::SingleChoice { options: args.options },
+        Kind::MultiChoice => InteractionKind::MultiChoice { options: args.options },
+        Kind::FileApproval => InteractionKind::FileApproval { files: args.files },
+    };
+
     // 1. Register the question
     let response = client
         .post(format!("{}/agent/ask", server_url))
@@ -48,44 +104,66 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             agent_id: args.agent_id.clone(),
             session_id: args.session_id.clone(),
             question: args.question.clone(),
+            kind,
+            default_answer: args.default.clone(),
+            timeout_secs: args.timeout_secs,
         })
         .send()
         .await?;
 
     if !response.status().is_success() {
-        eprintln!("Failed to ask question: {}", response.status());
+        eprintln!("Failed to ask question: {} {}", response.status(), response.text().await.unwrap_or_default());
         std::process::exit(1);
     }
 
     let ask_res: AskResponse = response.json().await?;
     let interaction_id = ask_res.interaction_id;
+    let deadline = args.timeout_secs.map(|secs| Instant::now() + Duration::from_secs(secs));
 
-    // println!("Question asked. Waiting for answer...");
-
-    // 2. Poll for answer
+    // 2. Wait for the answer; the server holds each request until it arrives
     loop {
-        let status_res = client
-            .get(format!("{}/agent/ask/{}", server_url, interaction_id))
+        let wait_res = client
+            .get(format!("{}/agent/ask/{}/wait", server_url, interaction_id))
+            .query(&[("timeout", WAIT_SECS)])
+            .timeout(Duration::from_secs(WAIT_SECS + 10))
             .send()
             .await;
 
-        match status_res {
-            Ok(res) => {
-                if res.status().is_success() {
-                    let interaction: InteractionStatus = res.json().await?;
-                    if interaction.status == "Resolved" {
+        let mut reached = false;
+        if let Ok(res) = wait_res {
+            if res.status().is_success() {
+                reached = true;
+                let interaction: InteractionStatus = res.json().await?;
+                match interaction.status.as_str() {
+                    "Resolved" => {
                         if let Some(answer) = interaction.result {
                             println!("{}", answer);
                             break;
                         }
                     }
+                    "Expired" => timed_out(interaction.result.or(args.default)),
+                    _ => {}
                 }
             }
-            Err(_) => {}
         }
 
-        thread::sleep(Duration::from_secs(1));
+        // The server expires the question too; this covers losing contact with it
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline + Duration::from_secs(5)) {
+            timed_out(args.default);
+        }
+
+        if !reached {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
     }
 
     Ok(())
 }
+
+fn timed_out(default: Option<String>) -> ! {
+    if let Some(answer) = default {
+        println!("{}", answer);
+    }
+    eprintln!("No answer before the deadline");
+    std::process::exit(TIMED_OUT_EXIT_CODE);
+}
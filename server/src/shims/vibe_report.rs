@@ -16,6 +16,21 @@ struct Args {
 
     #[arg(short, long)]
     thought: Option<String>,
+
+    /// Prompt tokens consumed since the last report
+    #[arg(long)]
+    prompt_tokens: Option<u64>,
+
+    /// Completion tokens produced since the last report
+    #[arg(long)]
+    completion_tokens: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UsagePayload {
+    prompt_tokens: u64,
+    completion_tokens: u64,
 }
 
 #[derive(Serialize, Debug)]
@@ -24,6 +39,7 @@ struct ReportPayload {
     session_id: String,
     progress: u8,
     thought: Option<String>,
+    usage: Option<UsagePayload>,
 }
 
 #[tokio::main]
@@ -38,6 +54,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         session_id: args.session_id,
         progress: args.progress,
         thought: args.thought,
+        usage: (args.prompt_tokens.is_some() || args.completion_tokens.is_some()).then(|| UsagePayload {
+            prompt_tokens: args.prompt_tokens.unwrap_or(0),
+            completion_tokens: args.completion_tokens.unwrap_or(0),
+        }),
     };
 
     let res = client.post(format!("{}/agent/report", server_url))
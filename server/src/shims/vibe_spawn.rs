@@ -0,0 +1,81 @@
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long)]
+    agent_id: String,
+
+    #[arg(long)]
+    session_id: String,
+
+    /// What the child agent should do
+    #[arg(long)]
+    description: String,
+
+    #[arg(long)]
+    agent_type: Option<String>,
+
+    /// Task ids the child waits for; repeat for several
+    #[arg(long)]
+    depends_on: Vec<String>,
+
+    /// Path globs the child is expected to change; repeat for several
+    #[arg(long)]
+    must_touch: Vec<String>,
+
+    /// Path globs the child may also change; repeat for several
+    #[arg(long)]
+    may_touch: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct SpawnPayload {
+    agent_id: String,
+    session_id: String,
+    description: String,
+    agent_type: Option<String>,
+    depends_on: Vec<String>,
+    must_touch: Vec<String>,
+    may_touch: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SpawnResponse {
+    task_id: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let server_url = env::var("VIBE_SERVER_URL").unwrap_or_else(|_| "http://localhost:4110".to_string());
+    let client = reqwest::Client::new();
+
+    let payload = SpawnPayload {
+        agent_id: args.agent_id,
+        session_id: args.session_id,
+        description: args.description,
+        agent_type: args.agent_type,
+        depends_on: args.depends_on,
+        must_touch: args.must_touch,
+        may_touch: args.may_touch,
+    };
+
+    let res = client.post(format!("{}/agent/spawn", server_url))
+        .json(&payload)
+        .send()
+        .await?;
+
+    if !res.status().is_success() {
+        eprintln!("Failed to spawn agent: {} {}", res.status(), res.text().await.unwrap_or_default());
+        std::process::exit(1);
+    }
+
+    // The task id is all the caller needs, e.g. for `--depends-on` of later spawns
+    let spawned: SpawnResponse = res.json().await?;
+    println!("{}", spawned.task_id);
+    Ok(())
+}
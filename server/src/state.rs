@@ -6,6 +6,7 @@ use crate::{
     llm::LlmRegistry, profiles::ProfileCatalog, project_sessions::ProjectSession,
     sessions::SessionStore,
     agents::spawner::AgentSpawner,
+    agents::dispatcher::TaskDispatcher,
 };
 use parking_lot::RwLock;
 
@@ -19,5 +20,6 @@ pub struct AppState {
     pub project_sessions: Arc<RwLock<HashMap<String, ProjectSession>>>,
     pub agents: AgentRegistry,
     pub agent_spawner: AgentSpawner,
+    pub dispatcher: TaskDispatcher,
     pub server_root_dir: PathBuf, // Added this
 }
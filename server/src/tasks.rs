@@ -1,13 +1,586 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::agents::boundaries::path_globs;
+use crate::agents::registry::AgentStatus;
+
+/// Upper bounds for the limits a task graph may set, so agent-written values
+/// stay within what dates and durations can represent.
+pub const MAX_TASK_TIMEOUT_SECS: u64 = 7 * 24 * 60 * 60;
+pub const MAX_TASK_BACKOFF_SECS: u64 = 24 * 60 * 60;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub id: String,
     pub description: String,
     pub agent_type: Option<String>, // Allow specifying worker type, default to "worker"
+    #[serde(default)]
+    pub depends_on: Vec<String>, // Task ids that must complete before this one is released
+    #[serde(default)]
+    pub timeout_secs: Option<u64>, // Overrides the server-wide wall-clock limit for this task
+    #[serde(default)]
+    pub max_attempts: Option<u32>, // Overrides the schedule's retry policy
+    #[serde(default)]
+    pub backoff_secs: Option<u64>,
+    #[serde(default)]
+    pub must_touch: Vec<String>, // Path globs, relative to the project root, the worker is expected to change
+    #[serde(default)]
+    pub may_touch: Vec<String>, // Further globs it is allowed to change
+}
+
+impl Task {
+    /// A task for the default worker type, without dependencies, limits or
+    /// path boundaries of its own.
+    pub fn new(id: String, description: String) -> Self {
+        Self {
+            id,
+            description,
+            agent_type: None,
+            depends_on: Vec::new(),
+            timeout_secs: None,
+            max_attempts: None,
+            backoff_secs: None,
+            must_touch: Vec::new(),
+            may_touch: Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskGraph {
     pub tasks: Vec<Task>,
 }
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TaskGraphError {
+    #[error("duplicate task id '{0}'")]
+    DuplicateTask(String),
+    #[error("task '{task}' depends on unknown task '{dependency}'")]
+    UnknownDependency { task: String, dependency: String },
+    #[error("dependency cycle detected: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("task '{task}' has an invalid path pattern: {reason}")]
+    InvalidPathPattern { task: String, reason: String },
+    #[error("task '{task}' sets {field} above the maximum of {max}")]
+    LimitTooLarge { task: String, field: &'static str, max: u64 },
+}
+
+impl TaskGraph {
+    /// Checks that task ids are unique, that path patterns parse and limits are
+    /// in range, that every `depends_on` edge points at a task in the graph, and
+    /// that the edges form a DAG.
+    pub fn validate(&self) -> Result<(), TaskGraphError> {
+        self.validate_tasks()?;
+        let by_id: HashMap<&str, &Task> = self.tasks.iter().map(|task| (task.id.as_str(), task)).collect();
+
+        for task in &self.tasks {
+            for dependency in &task.depends_on {
+                if !by_id.contains_key(dependency.as_str()) {
+                    return Err(TaskGraphError::UnknownDependency {
+                        task: task.id.clone(),
+                        dependency: dependency.clone(),
+                    });
+                }
+            }
+        }
+
+        // Depth-first search; a task seen again while still on the stack closes a cycle.
+        let mut finished: HashSet<&str> = HashSet::new();
+        let mut stack: Vec<&str> = Vec::new();
+        for task in &self.tasks {
+            if let Some(cycle) = find_cycle(task.id.as_str(), &by_id, &mut finished, &mut stack) {
+                return Err(TaskGraphError::Cycle(cycle));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The checks of `validate` that do not need the rest of the session's
+    /// schedule: unique ids, valid path patterns and limits, and no task that
+    /// depends on itself.
+    pub fn validate_tasks(&self) -> Result<(), TaskGraphError> {
+        let mut ids = HashSet::new();
+        for task in &self.tasks {
+            if !ids.insert(task.id.as_str()) {
+                return Err(TaskGraphError::DuplicateTask(task.id.clone()));
+            }
+            if task.depends_on.contains(&task.id) {
+                return Err(TaskGraphError::Cycle(vec![task.id.clone(), task.id.clone()]));
+            }
+            for (field, value, max) in [
+                ("timeout_secs", task.timeout_secs, MAX_TASK_TIMEOUT_SECS),
+                ("backoff_secs", task.backoff_secs, MAX_TASK_BACKOFF_SECS),
+            ] {
+                if value.is_some_and(|value| value > max) {
+                    return Err(TaskGraphError::LimitTooLarge { task: task.id.clone(), field, max });
+                }
+            }
+            for patterns in [&task.must_touch, &task.may_touch] {
+                path_globs(patterns).map_err(|e| TaskGraphError::InvalidPathPattern {
+                    task: task.id.clone(),
+                    reason: e.to_string(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find_cycle<'a>(
+    id: &'a str,
+    by_id: &HashMap<&'a str, &'a Task>,
+    finished: &mut HashSet<&'a str>,
+    stack: &mut Vec<&'a str>,
+) -> Option<Vec<String>> {
+    if finished.contains(id) {
+        return None;
+    }
+    if let Some(position) = stack.iter().position(|entry| *entry == id) {
+        let mut cycle: Vec<String> = stack[position..].iter().map(|s| s.to_string()).collect();
+        cycle.push(id.to_string());
+        return Some(cycle);
+    }
+
+    stack.push(id);
+    for dependency in &by_id[id].depends_on {
+        if let Some(cycle) = find_cycle(dependency.as_str(), by_id, finished, stack) {
+            return Some(cycle);
+        }
+    }
+    stack.pop();
+    finished.insert(id);
+    None
+}
+
+/// Scheduling state of a single task within a session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskState {
+    Pending,
+    Running { agent_id: String },
+    Completed,
+    Failed(String),
+    Blocked(String),
+}
+
+/// How many times a failing task is attempted, and the delay before the first
+/// retry. The delay doubles after every further failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub backoff_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 1, backoff_secs: 0 }
+    }
+}
+
+impl RetryPolicy {
+    /// Applies the overrides a task carries in the task graph.
+    pub fn for_task(&self, task: &Task) -> Self {
+        Self {
+            max_attempts: task.max_attempts.unwrap_or(self.max_attempts),
+            backoff_secs: task.backoff_secs.unwrap_or(self.backoff_secs),
+        }
+    }
+
+    /// Delay before the attempt following `failed_attempts` failures. Delays too
+    /// long to represent saturate.
+    pub fn delay(&self, failed_attempts: u32) -> TimeDelta {
+        let factor = 1u64 << failed_attempts.saturating_sub(1).min(16);
+        i64::try_from(self.backoff_secs.saturating_mul(factor))
+            .ok()
+            .and_then(TimeDelta::try_seconds)
+            .unwrap_or(TimeDelta::MAX)
+    }
+}
+
+/// The agent and error of the most recent failed attempt at a task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedAttempt {
+    pub agent_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    pub task: Task,
+    pub state: TaskState,
+    #[serde(default)]
+    pub attempts: u32, // Attempts started so far, including the running one
+    #[serde(default)]
+    pub last_failure: Option<FailedAttempt>,
+    #[serde(default)]
+    pub retry_at: Option<DateTime<Utc>>, // Pending retries are held back until then
+    #[serde(default)]
+    pub agent_id: Option<String>, // Agent of the latest attempt
+    #[serde(default)]
+    pub requested_by: Option<String>, // Agent that proposed the task; parent of the agents working on it
+}
+
+/// What changed in a schedule during `TaskSchedule::refresh`.
+#[derive(Debug, Default)]
+pub struct RefreshOutcome {
+    pub blocked: Vec<String>,
+    pub retried: Vec<String>,
+    pub failed: Vec<(String, String)>, // (task_id, reason) of tasks that ran out of attempts
+}
+
+/// The task DAG of one session, in the order tasks were dispatched.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskSchedule {
+    tasks: Vec<ScheduledTask>,
+    #[serde(default)]
+    retry_policy: RetryPolicy,
+}
+
+impl TaskSchedule {
+    pub fn new() -> Self {
+        Self::with_retry_policy(RetryPolicy::default())
+    }
+
+    pub fn with_retry_policy(retry_policy: RetryPolicy) -> Self {
+        Self { tasks: Vec::new(), retry_policy }
+    }
+
+    pub fn contains(&self, task_id: &str) -> bool {
+        self.tasks.iter().any(|entry| entry.task.id == task_id)
+    }
+
+    pub fn tasks(&self) -> &[ScheduledTask] {
+        &self.tasks
+    }
+
+    pub fn get(&self, task_id: &str) -> Option<&ScheduledTask> {
+        self.tasks.iter().find(|entry| entry.task.id == task_id)
+    }
+
+    /// Returns the task currently being worked on by `agent_id`.
+    pub fn task_for_agent(&self, agent_id: &str) -> Option<&Task> {
+        self.tasks
+            .iter()
+            .find(|entry| matches!(&entry.state, TaskState::Running { agent_id: id } if id == agent_id))
+            .map(|entry| &entry.task)
+    }
+
+    /// Adds the tasks of `graph` that are not scheduled yet. The merged graph is
+    /// validated first, so nothing is added if it would introduce a cycle or a
+    /// dangling dependency. Returns the number of tasks added.
+    pub fn merge(&mut self, graph: TaskGraph) -> Result<usize, TaskGraphError> {
+        self.merge_from(graph, None)
+    }
+
+    /// Like `merge`, recording the agent that proposed the tasks.
+    pub fn merge_from(&mut self, graph: TaskGraph, requested_by: Option<&str>) -> Result<usize, TaskGraphError> {
+        let new_tasks: Vec<Task> = graph
+            .tasks
+            .into_iter()
+            .filter(|task| !self.contains(&task.id))
+            .collect();
+
+        let combined = TaskGraph {
+            tasks: self
+                .tasks
+                .iter()
+                .map(|entry| entry.task.clone())
+                .chain(new_tasks.iter().cloned())
+                .collect(),
+        };
+        combined.validate()?;
+
+        let added = new_tasks.len();
+        self.tasks.extend(new_tasks.into_iter().map(|task| ScheduledTask {
+            task,
+            state: TaskState::Pending,
+            attempts: 0,
+            last_failure: None,
+            retry_at: None,
+            agent_id: None,
+            requested_by: requested_by.map(str::to_string),
+        }));
+        Ok(added)
+    }
+
+    pub fn set_state(&mut self, task_id: &str, state: TaskState) {
+        if let Some(entry) = self.tasks.iter_mut().find(|entry| entry.task.id == task_id) {
+            entry.state = state;
+        }
+    }
+
+    /// Marks a task as running on `agent_id` and counts the attempt.
+    pub fn start(&mut self, task_id: &str, agent_id: &str) {
+        if let Some(entry) = self.tasks.iter_mut().find(|entry| entry.task.id == task_id) {
+            entry.state = TaskState::Running { agent_id: agent_id.to_string() };
+            entry.attempts += 1;
+            entry.retry_at = None;
+            entry.agent_id = Some(agent_id.to_string());
+        }
+    }
+
+    /// Returns the task whose latest attempt was made by `agent_id`, whatever its state.
+    pub fn task_run_by(&self, agent_id: &str) -> Option<&ScheduledTask> {
+        self.tasks.iter().find(|entry| entry.agent_id.as_deref() == Some(agent_id))
+    }
+
+    /// Syncs running tasks with their agents' status, re-queues failed tasks
+    /// that have attempts left, and marks pending tasks whose predecessors
+    /// failed (or are themselves blocked) as blocked.
+    pub fn refresh(&mut self, agent_status: impl Fn(&str) -> Option<AgentStatus>) -> RefreshOutcome {
+        let mut outcome = RefreshOutcome::default();
+        let now = Utc::now();
+        for entry in &mut self.tasks {
+            let TaskState::Running { agent_id } = &entry.state else {
+                continue;
+            };
+            match agent_status(agent_id) {
+                Some(AgentStatus::Completed) => entry.state = TaskState::Completed,
+                Some(AgentStatus::Failed(reason)) => {
+                    let policy = self.retry_policy.for_task(&entry.task);
+                    // Tasks restored from older snapshots have not counted their attempt
+                    entry.attempts = entry.attempts.max(1);
+                    if entry.attempts < policy.max_attempts {
+                        let delay = policy.delay(entry.attempts);
+                        entry.retry_at = Some(now.checked_add_signed(delay).unwrap_or(DateTime::<Utc>::MAX_UTC));
+                        entry.last_failure = Some(FailedAttempt { agent_id: agent_id.clone(), reason });
+                        entry.state = TaskState::Pending;
+                        outcome.retried.push(entry.task.id.clone());
+                    } else {
+                        outcome.failed.push((entry.task.id.clone(), reason.clone()));
+                        entry.state = TaskState::Failed(reason);
+                    }
+                }
+                // Killed on request, so not worth another attempt
+                Some(AgentStatus::Terminated) => {
+                    let reason = "agent terminated".to_string();
+                    outcome.failed.push((entry.task.id.clone(), reason.clone()));
+                    entry.state = TaskState::Failed(reason);
+                }
+                _ => {}
+            }
+        }
+
+        // Blocking propagates along edges, so repeat until nothing changes.
+        loop {
+            let mut changed = false;
+            for index in 0..self.tasks.len() {
+                if self.tasks[index].state != TaskState::Pending {
+                    continue;
+                }
+                let failed_dependency = self.tasks[index]
+                    .task
+                    .depends_on
+                    .iter()
+                    .find(|dependency| {
+                        matches!(
+                            self.get(dependency).map(|entry| &entry.state),
+                            Some(TaskState::Failed(_)) | Some(TaskState::Blocked(_))
+                        )
+                    })
+                    .cloned();
+                if let Some(dependency) = failed_dependency {
+                    self.tasks[index].state =
+                        TaskState::Blocked(format!("dependency '{}' did not complete", dependency));
+                    outcome.blocked.push(self.tasks[index].task.id.clone());
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        outcome
+    }
+
+    /// Returns the first pending task whose predecessors have all completed
+    /// and whose retry backoff, if any, has elapsed.
+    pub fn next_ready(&self) -> Option<&Task> {
+        let now = Utc::now();
+        self.tasks
+            .iter()
+            .filter(|entry| entry.state == TaskState::Pending)
+            .filter(|entry| entry.retry_at.is_none_or(|at| at <= now))
+            .find(|entry| {
+                entry.task.depends_on.iter().all(|dependency| {
+                    self.get(dependency)
+                        .is_some_and(|dep| dep.state == TaskState::Completed)
+                })
+            })
+            .map(|entry| &entry.task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, depends_on: &[&str]) -> Task {
+        Task {
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            ..Task::new(id.to_string(), format!("Do {}", id))
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_cycle() {
+        let graph = TaskGraph {
+            tasks: vec![task("a", &["c"]), task("b", &["a"]), task("c", &["b"])],
+        };
+        match graph.validate() {
+            Err(TaskGraphError::Cycle(path)) => {
+                assert_eq!(path.first(), path.last());
+                assert_eq!(path.len(), 4);
+            }
+            other => panic!("expected cycle error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_dependency() {
+        let graph = TaskGraph {
+            tasks: vec![task("a", &["missing"])],
+        };
+        assert_eq!(
+            graph.validate(),
+            Err(TaskGraphError::UnknownDependency {
+                task: "a".to_string(),
+                dependency: "missing".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_path_pattern() {
+        let mut bad = task("a", &[]);
+        bad.may_touch = vec!["src/[".to_string()];
+        let graph = TaskGraph { tasks: vec![bad] };
+        assert!(matches!(graph.validate(), Err(TaskGraphError::InvalidPathPattern { task, .. }) if task == "a"));
+    }
+
+    #[test]
+    fn test_validate_rejects_huge_limits() {
+        let mut slow = task("a", &[]);
+        slow.backoff_secs = Some(10_000_000_000_000_000);
+        let graph = TaskGraph { tasks: vec![slow] };
+        assert!(matches!(graph.validate(), Err(TaskGraphError::LimitTooLarge { field: "backoff_secs", .. })));
+
+        let mut slow = task("a", &[]);
+        slow.timeout_secs = Some(MAX_TASK_TIMEOUT_SECS + 1);
+        let graph = TaskGraph { tasks: vec![slow] };
+        assert!(matches!(graph.validate(), Err(TaskGraphError::LimitTooLarge { field: "timeout_secs", .. })));
+    }
+
+    #[test]
+    fn test_depends_on_defaults_to_empty() {
+        let graph: TaskGraph =
+            serde_json::from_str(r#"{"tasks":[{"id":"a","description":"x","agent_type":null}]}"#)
+                .unwrap();
+        assert!(graph.tasks[0].depends_on.is_empty());
+        assert!(graph.validate().is_ok());
+    }
+
+    #[test]
+    fn test_schedule_releases_after_predecessor_completes() {
+        let mut schedule = TaskSchedule::new();
+        schedule
+            .merge(TaskGraph {
+                tasks: vec![task("b", &["a"]), task("a", &[])],
+            })
+            .unwrap();
+
+        assert_eq!(schedule.next_ready().map(|t| t.id.as_str()), Some("a"));
+        schedule.set_state("a", TaskState::Running { agent_id: "agent-a".to_string() });
+        schedule.refresh(|_| Some(AgentStatus::Running));
+        assert!(schedule.next_ready().is_none());
+
+        schedule.refresh(|_| Some(AgentStatus::Completed));
+        assert_eq!(schedule.next_ready().map(|t| t.id.as_str()), Some("b"));
+    }
+
+    #[test]
+    fn test_schedule_blocks_dependents_of_failed_task() {
+        let mut schedule = TaskSchedule::new();
+        schedule
+            .merge(TaskGraph {
+                tasks: vec![task("a", &[]), task("b", &["a"]), task("c", &["b"])],
+            })
+            .unwrap();
+        schedule.set_state("a", TaskState::Running { agent_id: "agent-a".to_string() });
+        schedule.refresh(|_| Some(AgentStatus::Failed("boom".to_string())));
+
+        assert!(matches!(schedule.get("b").unwrap().state, TaskState::Blocked(_)));
+        assert!(matches!(schedule.get("c").unwrap().state, TaskState::Blocked(_)));
+        assert!(schedule.next_ready().is_none());
+    }
+
+    #[test]
+    fn test_failed_task_is_retried_until_attempts_run_out() {
+        let mut schedule = TaskSchedule::with_retry_policy(RetryPolicy { max_attempts: 2, backoff_secs: 0 });
+        schedule
+            .merge(TaskGraph {
+                tasks: vec![task("a", &[]), task("b", &["a"])],
+            })
+            .unwrap();
+
+        schedule.start("a", "agent-1");
+        let outcome = schedule.refresh(|_| Some(AgentStatus::Failed("boom".to_string())));
+        assert_eq!(outcome.retried, vec!["a".to_string()]);
+        assert!(outcome.blocked.is_empty());
+        let entry = schedule.get("a").unwrap();
+        assert_eq!(entry.state, TaskState::Pending);
+        assert_eq!(entry.last_failure.as_ref().map(|f| f.agent_id.as_str()), Some("agent-1"));
+        assert_eq!(schedule.next_ready().map(|t| t.id.as_str()), Some("a"));
+
+        schedule.start("a", "agent-2");
+        let outcome = schedule.refresh(|_| Some(AgentStatus::Failed("boom again".to_string())));
+        assert_eq!(outcome.failed, vec![("a".to_string(), "boom again".to_string())]);
+        assert_eq!(outcome.blocked, vec!["b".to_string()]);
+        assert_eq!(schedule.get("a").unwrap().attempts, 2);
+    }
+
+    #[test]
+    fn test_retry_waits_for_backoff() {
+        let mut schedule = TaskSchedule::new();
+        let mut slow = task("a", &[]);
+        slow.max_attempts = Some(3);
+        slow.backoff_secs = Some(60);
+        schedule.merge(TaskGraph { tasks: vec![slow] }).unwrap();
+
+        schedule.start("a", "agent-1");
+        schedule.refresh(|_| Some(AgentStatus::Failed("boom".to_string())));
+        assert!(schedule.next_ready().is_none());
+
+        let policy = RetryPolicy { max_attempts: 3, backoff_secs: 60 };
+        assert_eq!(policy.delay(1), TimeDelta::seconds(60));
+        assert_eq!(policy.delay(2), TimeDelta::seconds(120));
+
+        // Configured backoffs are not bounded like task graph ones, so huge ones saturate
+        let huge = RetryPolicy { max_attempts: 3, backoff_secs: u64::MAX };
+        assert_eq!(huge.delay(2), TimeDelta::MAX);
+        let mut schedule = TaskSchedule::with_retry_policy(huge);
+        schedule.merge(TaskGraph { tasks: vec![task("a", &[])] }).unwrap();
+        schedule.start("a", "agent-1");
+        schedule.refresh(|_| Some(AgentStatus::Failed("boom".to_string())));
+        assert_eq!(schedule.get("a").unwrap().retry_at, Some(DateTime::<Utc>::MAX_UTC));
+    }
+
+    #[test]
+    fn test_merge_rejects_cycle_through_existing_tasks() {
+        let mut schedule = TaskSchedule::new();
+        schedule
+            .merge(TaskGraph {
+                tasks: vec![task("a", &[])],
+            })
+            .unwrap();
+        // "a" is already scheduled, so its new edge is ignored; "b" -> "b" is still a cycle.
+        let result = schedule.merge(TaskGraph {
+            tasks: vec![task("b", &["a", "b"])],
+        });
+        assert!(matches!(result, Err(TaskGraphError::Cycle(_))));
+        assert!(!schedule.contains("b"));
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+/// Placeholders prompt templates may use, as `{{name}}`.
+pub const TEMPLATE_VARIABLES: [&str; 7] = [
+    "agent_id",
+    "session_id",
+    "task.id",
+    "task.description",
+    "project.name",
+    "project.root",
+    "previous_results",
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("unclosed placeholder on line {0}")]
+    Unclosed(usize),
+    #[error("unknown template variable '{{{{{variable}}}}}' on line {line}")]
+    UnknownVariable { variable: String, line: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Variable(String),
+}
+
+/// A prompt with `{{name}}` placeholders. Text outside placeholders is kept as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses `source`, rejecting placeholders that are unclosed or name a
+    /// variable outside [`TEMPLATE_VARIABLES`].
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            let line = source[..source.len() - rest.len() + start].matches('\n').count() + 1;
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            let end = after.find("}}").ok_or(TemplateError::Unclosed(line))?;
+            let variable = after[..end].trim();
+            if !TEMPLATE_VARIABLES.contains(&variable) {
+                return Err(TemplateError::UnknownVariable {
+                    variable: variable.to_string(),
+                    line,
+                });
+            }
+            parts.push(Part::Variable(variable.to_string()));
+            rest = &after[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Substitutes `values`; variables without a value render empty.
+    pub fn render(&self, values: &HashMap<&str, String>) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.as_str(),
+                Part::Variable(name) => values.get(name.as_str()).map(String::as_str).unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_errors() {
+        let template = Template::parse("Agent {{agent_id}} works on {{ task.description }}.{{previous_results}}").unwrap();
+        let values = HashMap::from([
+            ("agent_id", "a1".to_string()),
+            ("task.description", "the docs".to_string()),
+        ]);
+        assert_eq!(template.render(&values), "Agent a1 works on the docs.");
+
+        // Single braces, as in JSON examples, are plain text
+        let json = Template::parse("{ \"tasks\": [] }").unwrap();
+        assert_eq!(json.render(&values), "{ \"tasks\": [] }");
+
+        assert_eq!(
+            Template::parse("line\n{{agent}}"),
+            Err(TemplateError::UnknownVariable {
+                variable: "agent".to_string(),
+                line: 2
+            })
+        );
+        assert_eq!(Template::parse("{{agent_id"), Err(TemplateError::Unclosed(1)));
+    }
+}
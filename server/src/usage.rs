@@ -0,0 +1,123 @@
+use std::{collections::HashMap, fs, ops::AddAssign, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Token counts reported by a provider for one or more completions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn new(prompt_tokens: u64, completion_tokens: u64) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens.saturating_add(self.completion_tokens)
+    }
+
+    /// Reads the usage stored under `meta.usage` of a session message, if any.
+    pub fn from_meta(meta: &Value) -> Option<Self> {
+        meta.get("usage")
+            .and_then(|usage| serde_json::from_value(usage.clone()).ok())
+    }
+}
+
+/// Counts come from agent reports, so they saturate rather than overflow.
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens = self.prompt_tokens.saturating_add(other.prompt_tokens);
+        self.completion_tokens = self.completion_tokens.saturating_add(other.completion_tokens);
+    }
+}
+
+impl std::iter::Sum for TokenUsage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(TokenUsage::default(), |mut total, usage| {
+            total += usage;
+            total
+        })
+    }
+}
+
+/// USD price per million tokens for one model.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+/// Per-model prices used to estimate cost, loaded from a JSON object keyed by model name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl PriceTable {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let raw = fs::read_to_string(path.as_ref())?;
+        Ok(serde_json::from_str(&raw)?)
+    }
+
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        self.prices.get(model).copied()
+    }
+
+    /// Returns the estimated cost in USD, or `None` when the model has no price.
+    pub fn estimate_cost(&self, model: &str, usage: &TokenUsage) -> Option<f64> {
+        let price = self.price(model)?;
+        Some(
+            usage.prompt_tokens as f64 * price.input_per_million / 1_000_000.0
+                + usage.completion_tokens as f64 * price.output_per_million / 1_000_000.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_usage_saturates_instead_of_overflowing() {
+        let huge = TokenUsage::new(u64::MAX, u64::MAX);
+        let mut usage = huge;
+        usage += huge;
+        assert_eq!(usage, huge);
+        assert_eq!(usage.total_tokens(), u64::MAX);
+        assert_eq!([huge, TokenUsage::new(1, 1)].into_iter().sum::<TokenUsage>(), huge);
+    }
+
+    #[test]
+    fn test_estimate_cost_uses_model_price() {
+        let table: PriceTable = serde_json::from_value(json!({
+            "gemini-2.0-flash": { "inputPerMillion": 0.1, "outputPerMillion": 0.4 }
+        }))
+        .unwrap();
+        let usage = TokenUsage::new(1_000_000, 500_000);
+
+        let cost = table.estimate_cost("gemini-2.0-flash", &usage).unwrap();
+        assert!((cost - 0.3).abs() < 1e-9);
+        assert!(table.estimate_cost("unknown", &usage).is_none());
+    }
+
+    #[test]
+    fn test_usage_from_meta_and_sum() {
+        let meta = json!({ "usage": { "promptTokens": 3, "completionTokens": 4 } });
+        let usage = TokenUsage::from_meta(&meta).unwrap();
+        assert_eq!(usage.total_tokens(), 7);
+        assert!(TokenUsage::from_meta(&Value::Null).is_none());
+
+        let total: TokenUsage = vec![usage, usage].into_iter().sum();
+        assert_eq!(total, TokenUsage::new(6, 8));
+    }
+}
@@ -29,24 +29,45 @@ pub fn spawn_and_capture_output(
     if !output.status.success() {
         // Return an error if the command itself failed (non-zero exit code)
         let error_message = format!(
-            "Command '{} {}' failed with exit code {:?}\nStdout: {}\nStderr: {}",
+            "Command '{} {}' failed with exit code {}\nStdout: {}\nStderr: {}",
             command,
             args.join(" "),
-            output.status.code(),
+            output
+                .status
+                .code()
+                .map_or_else(|| "none".to_string(), |code| code.to_string()),
             stdout,
             stderr
         );
-        return Err(io::Error::new(io::ErrorKind::Other, error_message));
+        return Err(io::Error::other(error_message));
     }
 
     Ok((stdout, stderr))
 }
 
+/// Sends `signal` (e.g. "TERM", "STOP") to every process in the group led by `pgid`.
+pub fn signal_process_group(pgid: u32, signal: &str) -> io::Result<()> {
+    spawn_and_capture_output("kill", &["-s", signal, "--", &format!("-{}", pgid)]).map(|_| ())
+}
+
+/// Returns whether any process in the group led by `pgid` is still alive.
+pub fn process_group_alive(pgid: u32) -> bool {
+    spawn_and_capture_output("kill", &["-0", "--", &format!("-{}", pgid)]).is_ok()
+}
+
+/// Returns when the process `pid` started, as reported by `ps`, or `None` if
+/// there is no such process. Together with the pid this identifies a process,
+/// as pids are reused.
+pub fn process_start_time(pid: u32) -> Option<String> {
+    let (stdout, _) = spawn_and_capture_output("ps", &["-o", "lstart=", "-p", &pid.to_string()]).ok()?;
+    let started = stdout.trim();
+    (!started.is_empty()).then(|| started.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
-    use std::path::Path;
     use tempfile::tempdir;
 
     #[test]
@@ -98,6 +119,13 @@ mod tests {
         assert!(err.to_string().contains("failed with exit code 1"));
     }
 
+    #[test]
+    fn test_process_start_time_of_own_process() {
+        let started = process_start_time(std::process::id()).expect("own process exists");
+        assert_eq!(process_start_time(std::process::id()), Some(started));
+        assert!(process_start_time(u32::MAX).is_none());
+    }
+
     #[test]
     fn test_spawn_and_capture_with_input_file() {
         let dir = tempdir().expect("Failed to create temporary directory");
@@ -1,12 +1,14 @@
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use chrono::{SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::{self, json};
 use thiserror::Error;
+use tracing::warn;
 
+use crate::agents::boundaries::path_globs;
 use crate::global_registry::{
     load_or_init_registry, save_registry, upsert_project, ProjectSummary, RegistryError,
 };
@@ -28,11 +30,87 @@ pub struct VibeProjectConfig {
     pub notes: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// What applies to files matching one `file_type_overrides` glob.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileTypeRule {
+    #[serde(default)]
+    pub instructions: Option<String>, // Added to the instructions of every worker
+    #[serde(default)]
+    pub formatter: Option<String>, // Check command, e.g. "cargo fmt --check"
+    #[serde(default)]
+    pub linter: Option<String>, // Check command, e.g. "cargo clippy -- -D warnings"
+}
+
+/// The contents of `.vibe/config/rules.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct VibeRules {
     pub version: u32,
-    #[serde(default = "empty_object")]
-    pub file_type_overrides: serde_json::Value,
+    #[serde(default)]
+    pub file_type_overrides: BTreeMap<String, FileTypeRule>, // path glob -> rule
+    #[serde(default)]
+    pub forbidden_paths: Vec<String>, // Globs workers must never change
+}
+
+impl Default for VibeRules {
+    fn default() -> Self {
+        Self {
+            version: RULES_VERSION,
+            file_type_overrides: BTreeMap::new(),
+            forbidden_paths: Vec::new(),
+        }
+    }
+}
+
+impl VibeRules {
+    /// Describes the rules for a worker's instructions.
+    pub fn instructions(&self) -> Option<String> {
+        if self.file_type_overrides.is_empty() && self.forbidden_paths.is_empty() {
+            return None;
+        }
+        let mut text = String::from("\n## Project rules\n\n");
+        for (pattern, rule) in &self.file_type_overrides {
+            text.push_str(&format!("Files matching `{}`:\n", pattern));
+            if let Some(instructions) = &rule.instructions {
+                text.push_str(&format!("- {}\n", instructions));
+            }
+            for command in rule.formatter.iter().chain(rule.linter.iter()) {
+                text.push_str(&format!("- must pass `{}`\n", command));
+            }
+        }
+        if !self.forbidden_paths.is_empty() {
+            text.push_str(&format!("Never change files matching: {}\n", self.forbidden_paths.join(", ")));
+        }
+        if !self.check_commands(None).is_empty() {
+            text.push_str("The checks are run after you finish; a failing check fails your task.\n");
+        }
+        Some(text)
+    }
+
+    /// Formatter and linter commands of the rules whose glob matches one of
+    /// `changed`, or of every rule when the changed files are unknown.
+    pub fn check_commands(&self, changed: Option<&[String]>) -> Vec<String> {
+        let mut commands: Vec<String> = Vec::new();
+        for (pattern, rule) in &self.file_type_overrides {
+            if let Some(changed) = changed {
+                let glob = match path_globs(std::slice::from_ref(pattern)) {
+                    Ok(glob) => glob,
+                    Err(err) => {
+                        warn!("Skipping the checks of invalid rules glob `{}`: {}", pattern, err);
+                        continue;
+                    }
+                };
+                if !changed.iter().any(|file| glob.is_match(file)) {
+                    continue;
+                }
+            }
+            for command in rule.formatter.iter().chain(rule.linter.iter()) {
+                if !commands.contains(command) {
+                    commands.push(command.clone());
+                }
+            }
+        }
+        commands
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -51,6 +129,8 @@ pub enum InitError {
     Json(#[from] serde_json::Error),
     #[error("unable to determine project root name at {0}")]
     InvalidProjectName(String),
+    #[error("invalid glob in rules.json: {0}")]
+    InvalidGlob(#[from] globset::Error),
 }
 
 pub fn init_vibe_project(root: &Path) -> Result<InitStatus, InitError> {
@@ -70,7 +150,7 @@ pub fn init_vibe_project(root: &Path) -> Result<InitStatus, InitError> {
     }
 
     if !rules_path.exists() {
-        let rules = default_rules();
+        let rules = VibeRules::default();
         write_json_pretty(&rules_path, &rules)?;
     }
 
@@ -98,6 +178,22 @@ pub fn init_vibe_project(root: &Path) -> Result<InitStatus, InitError> {
     })
 }
 
+/// Loads the project's rules; a project without `rules.json` has none.
+/// Every override and forbidden path must be a valid glob.
+pub fn load_rules(root: &Path) -> Result<VibeRules, InitError> {
+    let rules_path = rules_config_path(root);
+    if !rules_path.exists() {
+        return Ok(VibeRules::default());
+    }
+    let contents = fs::read_to_string(rules_path)?;
+    let rules: VibeRules = serde_json::from_str(&contents)?;
+    for pattern in rules.file_type_overrides.keys() {
+        path_globs(std::slice::from_ref(pattern))?;
+    }
+    path_globs(&rules.forbidden_paths)?;
+    Ok(rules)
+}
+
 pub fn load_project_config(root: &Path) -> Result<VibeProjectConfig, InitError> {
     let config_path = project_config_path(root);
     let contents = fs::read_to_string(config_path)?;
@@ -178,13 +274,6 @@ fn default_project_config(root: &Path) -> Result<VibeProjectConfig, InitError> {
     })
 }
 
-fn default_rules() -> VibeRules {
-    VibeRules {
-        version: RULES_VERSION,
-        file_type_overrides: empty_object(),
-    }
-}
-
 fn project_config_path(root: &Path) -> PathBuf {
     root.join(".vibe").join("config").join("project.json")
 }
@@ -228,6 +317,11 @@ fn register_project_in_global_registry(config: &VibeProjectConfig) -> Result<(),
     Ok(())
 }
 
+/// Whether `content` is still the placeholder text `vibe init` scaffolded.
+pub fn is_scaffold_placeholder(content: &str) -> bool {
+    content == AGENTS_MD || content == DOCUMENTATION_MD || MODES_FILES.iter().any(|(_, text)| content == *text)
+}
+
 const AGENTS_MD: &str = "# Agents\n\nTODO: Define agents for this project.\n";
 const DOCUMENTATION_MD: &str =
     "# Documentation\n\nTODO: Document shared context for this project.\n";
@@ -250,7 +344,3 @@ const MODES_FILES: [(&str, &str); 4] = [
         "# DOC_SCRIBE\n\nTODO: Describe doc scribe behavior for this project.\n",
     ),
 ];
-
-fn empty_object() -> serde_json::Value {
-    json!({})
-}
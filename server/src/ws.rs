@@ -12,12 +12,12 @@ use axum::{
 };
 use futures::{stream::SplitSink, SinkExt, StreamExt};
 use serde::Deserialize;
-use serde_json::{json, Value};
+use serde_json::json;
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
 use crate::{
-    llm::{LlmMessage, LlmRequest, MessageRole},
+    llm::{LlmChunk, LlmMessage, LlmRequest, MessageRole},
     project_sessions::{get_session, update_last_active},
     sessions::WsEvent,
     state::AppState,
@@ -93,7 +93,16 @@ async fn handle_socket(state: AppState, socket: WebSocket) {
                                             break;
                                         }
                                     }
-                                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                        // Tell the client, so it can re-read agent state and backfill logs
+                                        let dropped = WsEvent::Error {
+                                            code: "events-dropped".into(),
+                                            message: format!("Missed {skipped} session events"),
+                                        };
+                                        if send_event(&sender_clone, dropped).await.is_err() {
+                                            break;
+                                        }
+                                    }
                                     Err(_) => break,
                                 }
                             }
@@ -111,11 +120,7 @@ async fn handle_socket(state: AppState, socket: WebSocket) {
                             send_event(&sender, WsEvent::SessionUpdated { session: summary }).await;
                     }
                 }
-                Ok(ClientWsMessage::UserMessage {
-                    session_id,
-                    content,
-                    ..
-                }) => {
+                Ok(ClientWsMessage::UserMessage { session_id, content }) => {
                     if Some(session_id.clone()) != active_session {
                         continue;
                     }
@@ -265,9 +270,11 @@ async fn run_orchestrator(state: AppState, session_id: String, user_content: Str
     match state.llms.stream(request) {
         Ok(mut stream) => {
             let mut buffer = String::new();
+            let mut usage = None;
             while let Some(chunk) = stream.next().await {
                 match chunk {
-                    Ok(text) => {
+                    Ok(LlmChunk::Usage(reported)) => usage = Some(reported),
+                    Ok(LlmChunk::Text(text)) => {
                         buffer.push_str(&text);
                         state
                             .sessions
@@ -298,7 +305,7 @@ async fn run_orchestrator(state: AppState, session_id: String, user_content: Str
             }
             state
                 .sessions
-                .update_assistant_message(&session_id, &message_id, &buffer)
+                .update_assistant_message(&session_id, &message_id, &buffer, usage)
                 .await;
             if let Some(summary) = state.sessions.summary(&session_id).await {
                 state
@@ -338,13 +345,26 @@ enum ClientWsMessage {
     JoinSession {
         session_id: String,
     },
+    // Fields the hub does not read, such as `meta` or a ping's `timestamp`, are ignored
     UserMessage {
         session_id: String,
         content: String,
-        #[serde(default)]
-        meta: Option<Value>,
-    },
-    Ping {
-        timestamp: i64,
     },
+    Ping {},
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_messages_ignore_unread_fields() {
+        let message = r#"{"type": "userMessage", "session_id": "s1", "content": "hi", "meta": {"source": "cli"}}"#;
+        assert!(matches!(
+            serde_json::from_str::<ClientWsMessage>(message),
+            Ok(ClientWsMessage::UserMessage { content, .. }) if content == "hi"
+        ));
+        let ping = r#"{"type": "ping", "timestamp": 1700000000}"#;
+        assert!(matches!(serde_json::from_str::<ClientWsMessage>(ping), Ok(ClientWsMessage::Ping {})));
+    }
 }
@@ -1,28 +1,37 @@
 use std::{collections::HashMap, net::IpAddr, str::FromStr, sync::Arc};
 
 use agent_hub_server::{
+    agents::{
+        dispatcher::TaskDispatcher,
+        registry::{Agent, AgentRegistry, AgentStatus},
+        spawner::AgentSpawner,
+    },
     config::ServerConfig,
-    global_registry::GlobalProjectRegistry,
+    global_registry::{GlobalProjectRegistry, ProjectSummary},
     llm::{LlmConfig, LlmRegistry, ProviderKind},
     profiles::ProfileCatalog,
-    project_sessions::{create_or_get_session_for_project, list_sessions, ProjectSessionStatus},
+    project_sessions::{
+        create_or_get_session_for_project, list_sessions, ProjectSession, ProjectSessionStatus,
+    },
+    runtime_state::{restore_projects, save_snapshot, RuntimeSnapshot, RUNTIME_STATE_VERSION},
     sessions::SessionStore,
     state::AppState,
-    ws,
+    tasks::{TaskGraph, TaskSchedule},
+    api, ws,
 };
 use axum::{
     body::Body,
     http::{Request, StatusCode},
 };
 use parking_lot::RwLock;
-use tempfile::tempdir;
+use tempfile::{tempdir, TempDir};
 use tower::ServiceExt;
 
-#[test]
-fn create_session_for_project_creates_entry() {
-    let state = test_state();
+#[tokio::test]
+async fn create_session_for_project_creates_entry() {
+    let (state, _server_root) = test_state();
     let project_root = "/tmp/vibe-project";
-    let session = create_or_get_session_for_project(&state, project_root, "Vibe Project");
+    let session = create_or_get_session_for_project(&state, project_root, "Vibe Project").await;
     assert_eq!(session.project_root, project_root);
     assert_eq!(session.status, ProjectSessionStatus::Active);
 
@@ -31,28 +40,68 @@ fn create_session_for_project_creates_entry() {
     assert_eq!(sessions[0].session_id, session.session_id);
 }
 
-#[test]
-fn create_or_get_reuses_active_session() {
-    let state = test_state();
+#[tokio::test]
+async fn create_or_get_reuses_active_session() {
+    let (state, _server_root) = test_state();
     let project_root = "/tmp/vibe-project";
 
-    let first = create_or_get_session_for_project(&state, project_root, "Vibe Project");
-    let second = create_or_get_session_for_project(&state, project_root, "Vibe Project");
+    let first = create_or_get_session_for_project(&state, project_root, "Vibe Project").await;
+    let second = create_or_get_session_for_project(&state, project_root, "Vibe Project").await;
     assert_eq!(
         first.session_id, second.session_id,
         "should reuse active session"
     );
 
-    let other = create_or_get_session_for_project(&state, "/tmp/another", "Another");
+    let other = create_or_get_session_for_project(&state, "/tmp/another", "Another").await;
     assert_ne!(
         first.session_id, other.session_id,
         "different project roots should create new sessions"
     );
 }
 
+#[tokio::test]
+async fn restored_session_respawns_lost_orchestrator() {
+    let (state, _server_root) = test_state();
+    let project = tempdir().expect("temp dir");
+    let project_root = project.path().to_string_lossy().to_string();
+    let mut orchestrator = Agent::new("restored".to_string(), "orchestrator".to_string());
+    orchestrator.status = AgentStatus::Running;
+    let snapshot = RuntimeSnapshot {
+        version: RUNTIME_STATE_VERSION,
+        sessions: vec![ProjectSession {
+            session_id: "restored".to_string(),
+            project_root: project_root.clone(),
+            project_name: "Restored".to_string(),
+            created_at: "2025-11-18T16:00:00Z".to_string(),
+            last_active_at: "2025-11-18T16:00:00Z".to_string(),
+            status: ProjectSessionStatus::Active,
+            latest_result: None,
+            usage: Default::default(),
+            rules: Default::default(),
+            orchestrator_lost: false,
+        }],
+        agents: vec![orchestrator],
+        schedules: Default::default(),
+    };
+    save_snapshot(project.path(), &snapshot).unwrap();
+    state.global_registry.write().projects.push(ProjectSummary {
+        project_root: project_root.clone(),
+        project_name: "Restored".to_string(),
+        last_seen: "2025-11-18T16:00:00Z".to_string(),
+    });
+
+    restore_projects(&state, &state.dispatcher);
+    assert!(list_sessions(&state)[0].orchestrator_lost);
+
+    let session = create_or_get_session_for_project(&state, &project_root, "Restored").await;
+    assert_eq!(session.session_id, "restored");
+    assert!(!session.orchestrator_lost);
+    assert!(!list_sessions(&state)[0].orchestrator_lost);
+}
+
 #[tokio::test]
 async fn project_session_ws_route_is_mounted() {
-    let state = test_state();
+    let (state, _server_root) = test_state();
     let app = ws::router(state);
     let request = Request::builder()
         .method("GET")
@@ -75,28 +124,222 @@ async fn project_session_ws_route_is_mounted() {
     );
 }
 
-fn test_state() -> AppState {
+#[tokio::test]
+async fn prompt_preview_layers_project_modes() {
+    let (state, _server_root) = test_state();
+    let project = tempdir().expect("temp dir");
+    let modes = project.path().join(".vibe").join("MODES");
+    std::fs::create_dir_all(&modes).unwrap();
+    std::fs::write(modes.join("WORKER.md"), "Run the tests before completing.\n").unwrap();
+    let project_root = project.path().to_string_lossy().to_string();
+    let session = create_or_get_session_for_project(&state, &project_root, "Preview").await;
+
+    let app = api::router(state);
+    let request = Request::builder()
+        .uri(format!("/project-sessions/{}/prompt?agent_type=worker", session.session_id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let prompt: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(prompt["mode"], "WORKER");
+    assert_eq!(prompt["layers"][0]["source"], ".vibe/MODES/WORKER.md");
+    assert!(prompt["text"].as_str().unwrap().starts_with("Run the tests before completing."));
+
+    let request = Request::builder()
+        .uri("/project-sessions/unknown/prompt")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn agent_endpoints_list_filter_and_detail() {
+    let (state, _server_root) = test_state();
+    let mut running = Agent::new("s1".to_string(), "worker".to_string());
+    running.status = AgentStatus::Running;
+    let running_id = running.id.clone();
+    state.agents.register_agent(running);
+    state.agents.register_agent(Agent::new("s2".to_string(), "orchestrator".to_string()));
+    // A worker restored from an older snapshot, with its task only in the schedule
+    let restored = Agent::new("s2".to_string(), "worker".to_string());
+    let restored_id = restored.id.clone();
+    state.agents.register_agent(restored);
+    let mut schedule = TaskSchedule::default();
+    schedule
+        .merge(TaskGraph {
+            tasks: vec![serde_json::from_value(serde_json::json!({"id": "t1", "description": "Write docs"})).unwrap()],
+        })
+        .unwrap();
+    schedule.start("t1", &restored_id);
+    state.dispatcher.restore_schedule("s2", schedule);
+    let app = api::router(state);
+
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+        }
+    };
+
+    let (_, all) = get("/agents".to_string()).await;
+    assert_eq!(all["agents"].as_array().unwrap().len(), 3);
+    let (_, filtered) = get("/agents?status=running".to_string()).await;
+    assert_eq!(filtered["agents"].as_array().unwrap().len(), 1);
+    assert_eq!(filtered["agents"][0]["id"], running_id.as_str());
+    let (_, session) = get("/sessions/s2/agents".to_string()).await;
+    assert_eq!(session["agents"][0]["agent_type"], "orchestrator");
+
+    let (status, detail) = get(format!("/agents/{}", running_id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(detail["status_label"], "running");
+    assert!(detail["task_id"].is_null());
+    let (_, detail) = get(format!("/agents/{}", restored_id)).await;
+    assert_eq!(detail["task_id"], "t1");
+    let (status, _) = get("/agents/unknown".to_string()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn spawned_tasks_and_agent_tree_record_parents() {
+    let (state, _server_root) = test_state();
+    let parent = Agent::new("s1".to_string(), "orchestrator".to_string());
+    let parent_id = parent.id.clone();
+    state.agents.register_agent(parent);
+    let child = Agent::new("s1".to_string(), "worker".to_string());
+    let child_id = child.id.clone();
+    state.agents.register_agent(child);
+    state
+        .agents
+        .set_lineage(&child_id, Some(parent_id.clone()), Some("t1".to_string()))
+        .unwrap();
+    let dispatcher = state.dispatcher.clone();
+    let app = api::router(state);
+
+    let spawn = |agent_id: String| {
+        let body = serde_json::json!({
+            "agent_id": agent_id,
+            "session_id": "s1",
+            "description": "write the changelog",
+        });
+        Request::builder()
+            .method("POST")
+            .uri("/agent/spawn")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let response = app.clone().oneshot(spawn(parent_id.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let task_id = serde_json::from_slice::<serde_json::Value>(&body).unwrap()["task_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let schedule = dispatcher.schedule_snapshot("s1").unwrap();
+    assert_eq!(schedule.get(&task_id).unwrap().requested_by.as_deref(), Some(parent_id.as_str()));
+    let response = app.clone().oneshot(spawn("unknown".to_string())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let request = Request::builder()
+        .uri("/sessions/s1/agents/tree")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let tree: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let roots = tree["agents"].as_array().unwrap();
+    let root = roots.iter().find(|node| node["id"] == parent_id.as_str()).unwrap();
+    assert_eq!(root["children"][0]["id"], child_id.as_str());
+    assert_eq!(root["children"][0]["task_id"], "t1");
+}
+
+#[tokio::test]
+async fn ask_rejects_timeouts_past_the_representable_deadline() {
+    let (state, _server_root) = test_state();
+    let agent = Agent::new("s1".to_string(), "worker".to_string());
+    let agent_id = agent.id.clone();
+    state.agents.register_agent(agent);
+    let app = api::router(state);
+
+    let ask = |timeout_secs: u64| {
+        let body = serde_json::json!({
+            "agent_id": agent_id,
+            "session_id": "s1",
+            "question": "Proceed?",
+            "timeout_secs": timeout_secs,
+        });
+        Request::builder()
+            .method("POST")
+            .uri("/agent/ask")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    for timeout_secs in [u64::MAX, i64::MAX as u64] {
+        let response = app.clone().oneshot(ask(timeout_secs)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let response = app.oneshot(ask(60)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// Builds the app state around a fresh server root, which is removed once
+/// the returned `TempDir` is dropped.
+fn test_state() -> (AppState, TempDir) {
     let prompt_temp = tempdir().expect("temp dir");
     let prompt_dir = prompt_temp.path().to_path_buf();
+    let server_root_dir = prompt_dir.clone();
     let profiles =
         Arc::new(ProfileCatalog::load(&prompt_dir).expect("empty profile directory should load"));
-    AppState {
-        config: ServerConfig {
-            host: IpAddr::from_str("127.0.0.1").unwrap(),
-            http_port: 4110,
-            ws_port: 4111,
-            shared_secret: None,
-            prompt_profile_dir: prompt_dir,
-            default_llm: LlmConfig {
-                provider: ProviderKind::Dummy,
-                model: "dummy".into(),
-                temperature: 0.2,
-            },
+    let agents = AgentRegistry::new();
+    let config = ServerConfig {
+        host: IpAddr::from_str("127.0.0.1").unwrap(),
+        http_port: 4110,
+        ws_port: 4111,
+        shared_secret: None,
+        prompt_profile_dir: prompt_dir,
+        prompt_profile: None,
+        default_llm: LlmConfig {
+            provider: ProviderKind::Dummy,
+            model: "dummy".into(),
+            temperature: 0.2,
         },
+        max_workers: 4,
+        max_workers_per_session: 2,
+        price_table: Default::default(),
+        agent_timeout_secs: 3600,
+        agent_idle_timeout_secs: 600,
+        task_max_attempts: 2,
+        task_retry_backoff_secs: 30,
+        worker_isolation: Default::default(),
+        merge_mode: Default::default(),
+        merge_conflict_policy: Default::default(),
+        boundary_policy: Default::default(),
+    };
+    let agent_spawner = AgentSpawner::new(agents.clone(), server_root_dir.clone());
+    let dispatcher = TaskDispatcher::new(
+        agent_spawner.clone(),
+        agents.clone(),
+        Arc::new(config.clone()),
+        server_root_dir.clone(),
+    );
+    let state = AppState {
+        config,
         sessions: Arc::new(SessionStore::new()),
         profiles,
         llms: Arc::new(LlmRegistry::new()),
         global_registry: Arc::new(RwLock::new(GlobalProjectRegistry::empty())),
         project_sessions: Arc::new(RwLock::new(HashMap::new())),
-    }
+        agents,
+        agent_spawner,
+        dispatcher,
+        server_root_dir,
+    };
+    (state, prompt_temp)
 }
@@ -5,7 +5,8 @@ use std::time::Duration;
 
 use agent_hub_server::global_registry::{load_or_init_registry, GLOBAL_HOME_OVERRIDE_ENV};
 use agent_hub_server::vibe_project::{
-    init_vibe_project, load_project_config, InitStatus, VibeProjectConfig, VIBE_SCHEMA_VERSION,
+    init_vibe_project, load_project_config, load_rules, InitError, InitStatus, VibeProjectConfig,
+    VibeRules, VIBE_SCHEMA_VERSION,
 };
 use tempfile::tempdir;
 
@@ -95,6 +96,58 @@ fn init_updates_global_registry() -> Result<(), Box<dyn std::error::Error>> {
     })
 }
 
+#[test]
+fn rules_load_typed_overrides() -> Result<(), Box<dyn std::error::Error>> {
+    with_temp_global_home(|| -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        assert_eq!(load_rules(dir.path())?, VibeRules::default());
+
+        // The scaffold's empty `file_type_overrides` object still parses
+        init_vibe_project(dir.path())?;
+        let rules = load_rules(dir.path())?;
+        assert!(rules.file_type_overrides.is_empty());
+        assert!(rules.instructions().is_none());
+
+        fs::write(
+            dir.path().join(".vibe/config/rules.json"),
+            r#"{
+                "version": 1,
+                "file_type_overrides": {
+                    "**/*.rs": { "instructions": "Use thiserror for errors.", "formatter": "cargo fmt --check", "linter": "cargo clippy" },
+                    "*.md": { "formatter": "markdownlint ." }
+                },
+                "forbidden_paths": ["Cargo.lock"]
+            }"#,
+        )?;
+        let rules = load_rules(dir.path())?;
+        let instructions = rules.instructions().expect("rules describe themselves");
+        assert!(instructions.contains("Use thiserror for errors."));
+        assert!(instructions.contains("Cargo.lock"));
+        assert_eq!(
+            rules.check_commands(Some(&["src/main.rs".to_string()])),
+            vec!["cargo fmt --check".to_string(), "cargo clippy".to_string()]
+        );
+        assert_eq!(rules.check_commands(None).len(), 3);
+        assert!(instructions.contains("a failing check fails your task"));
+
+        fs::write(
+            dir.path().join(".vibe/config/rules.json"),
+            r#"{ "version": 1, "forbidden_paths": [".env"] }"#,
+        )?;
+        let instructions = load_rules(dir.path())?
+            .instructions()
+            .expect("forbidden paths are described");
+        assert!(!instructions.contains("a failing check"));
+
+        fs::write(
+            dir.path().join(".vibe/config/rules.json"),
+            r#"{ "version": 1, "file_type_overrides": { "src/[.rs": { "linter": "cargo clippy" } } }"#,
+        )?;
+        assert!(matches!(load_rules(dir.path()), Err(InitError::InvalidGlob(_))));
+        Ok(())
+    })
+}
+
 fn write_config(
     path: &std::path::Path,
     config: &VibeProjectConfig,